@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+
+use amm::{
+    Action, ActionResult, AdminAction, DynamicFeeConfig, FarmView, GovernanceConfig,
+    LimitOrderView, MultisigConfig, MultisigRequestView, PendingTransferView, PoolInfo,
+    PoolStatsView, PoolView, PositionView, ProposalView, QueuedActionView, StakeView, SwapResult,
+    TimelockConfig, TwammOrderView,
+};
+use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
+use near_sdk::json_types::U128;
+use near_sdk::{AccountId, BlockHeight};
+
+use crate::{DEFAULT_GAS, TRANSFER_GAS};
+
+// Methods of AMM in the order of its modules. Callbacks AMM calls on itself and
+// `ft_on_transfer` called by tokens are left out, tokens are sent with `FtClient`
+contract_client! {
+    AmmClient {
+        calls {
+            // Init method of the contract, not a constructor of the client
+            #[allow(clippy::new_ret_no_self)]
+            fn new(NewArgs {
+                token_a_contract: AccountId,
+                token_b_contract: AccountId,
+                token_a_metadata: FungibleTokenMetadata,
+                token_b_metadata: FungibleTokenMetadata,
+            }) -> (), deposit = 0, gas = DEFAULT_GAS;
+
+            // Actions
+            fn execute(ExecuteArgs { actions: Vec<Action> })
+                -> Vec<ActionResult>, deposit = 1, gas = TRANSFER_GAS;
+
+            // Farming
+            fn stake_shares(StakeSharesArgs {
+                pool_id: u64,
+                shares: U128,
+                lock_duration: Option<u64>,
+            }) -> (), deposit = 0, gas = DEFAULT_GAS;
+            fn unstake_shares(UnstakeSharesArgs { pool_id: u64, shares: Option<U128> })
+                -> U128, deposit = 0, gas = DEFAULT_GAS;
+            fn claim_rewards(ClaimRewardsArgs { pool_id: u64 })
+                -> U128, deposit = 0, gas = DEFAULT_GAS;
+            fn set_farm(SetFarmArgs {
+                pool_id: u64,
+                reward_token_id: AccountId,
+                reward_per_second: U128,
+            }) -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn reclaim_farm_rewards(ReclaimFarmRewardsArgs { pool_id: u64 })
+                -> (), deposit = 1, gas = TRANSFER_GAS;
+
+            // Governance, proposals pay for their storage with the attached deposit
+            fn enable_governance(EnableGovernanceArgs { config: GovernanceConfig })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn lock_voting_tokens(LockVotingTokensArgs { amount: U128 })
+                -> (), deposit = 0, gas = DEFAULT_GAS;
+            fn unlock_voting_tokens(UnlockVotingTokensArgs { amount: U128 })
+                -> (), deposit = 0, gas = DEFAULT_GAS;
+            fn create_proposal(CreateProposalArgs { description: String, action: AdminAction })
+                -> u64, deposit = 0, gas = DEFAULT_GAS;
+            fn vote(VoteArgs { proposal_id: u64, support: bool })
+                -> (), deposit = 0, gas = DEFAULT_GAS;
+            fn execute_proposal(ExecuteProposalArgs { proposal_id: u64 })
+                -> (), deposit = 0, gas = TRANSFER_GAS;
+
+            // Pools and the ledger
+            fn swap(SwapArgs {
+                buy_token_name: AccountId,
+                sell_token_name: AccountId,
+                sell_amount: U128,
+                min_amount_out: Option<U128>,
+                referral_id: Option<AccountId>,
+                fee_bps: Option<u32>,
+            }) -> SwapResult, deposit = 0, gas = DEFAULT_GAS;
+            fn add_tokens_to_pool(AddTokensToPoolArgs {
+                token_a_name: AccountId,
+                token_a_amount: U128,
+                token_b_name: AccountId,
+                token_b_amount: U128,
+                fee_bps: Option<u32>,
+            }) -> (), deposit = 0, gas = DEFAULT_GAS;
+            fn exclude_tokens_from_pool(ExcludeTokensFromPoolArgs {
+                token_a_name: AccountId,
+                token_b_name: AccountId,
+                fee_bps: Option<u32>,
+            }) -> (), deposit = 0, gas = DEFAULT_GAS;
+            // Resolves to false if the transfer failed and tokens are back in the deposit
+            fn withdraw_tokens(WithdrawTokensArgs { token_name: AccountId, amount: U128 })
+                -> bool, deposit = 1, gas = TRANSFER_GAS;
+
+            // Orders
+            fn place_limit_order(PlaceLimitOrderArgs {
+                sell_token: AccountId,
+                buy_token: AccountId,
+                amount: U128,
+                limit_price: U128,
+                fee_bps: Option<u32>,
+            }) -> u64, deposit = 0, gas = DEFAULT_GAS;
+            fn cancel_limit_order(CancelLimitOrderArgs { order_id: u64 })
+                -> U128, deposit = 0, gas = DEFAULT_GAS;
+            fn place_twamm_order(PlaceTwammOrderArgs {
+                sell_token: AccountId,
+                buy_token: AccountId,
+                amount: U128,
+                blocks: BlockHeight,
+                fee_bps: Option<u32>,
+            }) -> u64, deposit = 0, gas = DEFAULT_GAS;
+            fn withdraw_twamm_proceeds(WithdrawTwammProceedsArgs { order_id: u64 })
+                -> U128, deposit = 0, gas = DEFAULT_GAS;
+            fn cancel_twamm_order(CancelTwammOrderArgs { order_id: u64 })
+                -> U128, deposit = 0, gas = DEFAULT_GAS;
+
+            // Multisig, requests pay for their storage with the attached deposit
+            fn set_multisig(SetMultisigArgs { config: MultisigConfig })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn create_multisig_request(CreateMultisigRequestArgs { action: AdminAction })
+                -> u64, deposit = 0, gas = DEFAULT_GAS;
+            fn confirm_multisig_request(ConfirmMultisigRequestArgs { request_id: u64 })
+                -> (), deposit = 1, gas = TRANSFER_GAS;
+            fn remove_expired_multisig_request(RemoveExpiredMultisigRequestArgs { request_id: u64 })
+                -> (), deposit = 0, gas = DEFAULT_GAS;
+
+            // Owner
+            fn set_treasury(SetTreasuryArgs { treasury_id: AccountId })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn list_token(ListTokenArgs { token_id: AccountId, metadata: FungibleTokenMetadata })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn add_pool(AddPoolArgs { token_a: AccountId, token_b: AccountId, fee_bps: u32 })
+                -> u64, deposit = 1, gas = DEFAULT_GAS;
+            fn set_fee(SetFeeArgs { pool_id: u64, fee_bps: u32 })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn set_dynamic_fee(SetDynamicFeeArgs { pool_id: u64, config: Option<DynamicFeeConfig> })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn set_max_price_impact(SetMaxPriceImpactArgs {
+                pool_id: u64,
+                max_price_impact_bps: Option<u32>,
+            }) -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn set_protocol_fee(SetProtocolFeeArgs { pool_id: u64, protocol_fee_bps: u32 })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn claim_protocol_fees(ClaimProtocolFeesArgs { pool_id: u64 })
+                -> (), deposit = 1, gas = TRANSFER_GAS;
+            fn set_referral(SetReferralArgs { referral_id: AccountId, referral_fee_bps: u32 })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn remove_referral(RemoveReferralArgs { referral_id: AccountId })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn skim(SkimArgs { token_id: AccountId, max_amount: U128 })
+                -> (), deposit = 1, gas = TRANSFER_GAS;
+            fn sync(SyncArgs { pool_id: u64, token_id: AccountId, max_amount: U128 })
+                -> (), deposit = 1, gas = TRANSFER_GAS;
+
+            // Storage, the deposit of `storage_deposit` is the storage balance to add
+            fn storage_deposit(StorageDepositArgs {
+                account_id: Option<AccountId>,
+                registration_only: Option<bool>,
+            }) -> StorageBalance, deposit = 0, gas = DEFAULT_GAS;
+            fn storage_withdraw(StorageWithdrawArgs { amount: Option<U128> })
+                -> StorageBalance, deposit = 1, gas = DEFAULT_GAS;
+            fn storage_unregister(StorageUnregisterArgs { force: Option<bool> })
+                -> bool, deposit = 1, gas = DEFAULT_GAS;
+
+            // Timelock, queued actions pay for their storage with the attached deposit
+            fn set_timelock(SetTimelockArgs { config: TimelockConfig })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+            fn queue_action(QueueActionArgs { action: AdminAction })
+                -> u64, deposit = 0, gas = DEFAULT_GAS;
+            fn execute_action(ExecuteActionArgs { action_id: u64 })
+                -> (), deposit = 0, gas = TRANSFER_GAS;
+            fn cancel_action(CancelActionArgs { action_id: u64 })
+                -> (), deposit = 1, gas = DEFAULT_GAS;
+
+            // NEAR, the deposit of `deposit_near` is the amount to wrap
+            fn deposit_near(DepositNearArgs {}) -> U128, deposit = 0, gas = DEFAULT_GAS;
+            fn withdraw_near(WithdrawNearArgs { amount: U128 })
+                -> bool, deposit = 1, gas = TRANSFER_GAS;
+        }
+        views {
+            // Farming
+            fn get_farm(GetFarmArgs { pool_id: u64 }) -> Option<FarmView>;
+            fn get_staked_shares(GetStakedSharesArgs { pool_id: u64, account_id: AccountId })
+                -> U128;
+            fn get_stake(GetStakeArgs { pool_id: u64, account_id: AccountId }) -> Option<StakeView>;
+            fn get_pending_rewards(GetPendingRewardsArgs { pool_id: u64, account_id: AccountId })
+                -> U128;
+
+            // Governance
+            fn get_governance(GetGovernanceArgs {}) -> Option<GovernanceConfig>;
+            fn get_voting_power(GetVotingPowerArgs { account_id: AccountId }) -> U128;
+            fn get_total_voting_power(GetTotalVotingPowerArgs {}) -> U128;
+            fn get_proposal(GetProposalArgs { proposal_id: u64 }) -> Option<ProposalView>;
+            fn get_proposals(GetProposalsArgs { from_index: u64, limit: u64 }) -> Vec<ProposalView>;
+
+            // Ledger
+            fn get_deposits(GetDepositsArgs { account_id: AccountId }) -> HashMap<AccountId, U128>;
+            fn get_deposit(GetDepositArgs { account_id: AccountId, token_id: AccountId }) -> U128;
+            fn get_depositors(GetDepositorsArgs {
+                token_id: AccountId,
+                from_index: u64,
+                limit: u64,
+            }) -> Vec<(AccountId, U128)>;
+            fn ft_balance_of(FtBalanceOfArgs { token_name: AccountId, account_id: AccountId })
+                -> U128;
+
+            // Orders
+            fn get_limit_order(GetLimitOrderArgs { order_id: u64 }) -> Option<LimitOrderView>;
+            fn get_account_limit_orders(GetAccountLimitOrdersArgs {
+                account_id: AccountId,
+                from_index: u64,
+                limit: u64,
+            }) -> Vec<LimitOrderView>;
+            fn get_pool_limit_orders(GetPoolLimitOrdersArgs {
+                pool_id: u64,
+                from_index: u64,
+                limit: u64,
+            }) -> Vec<LimitOrderView>;
+            fn get_twamm_order(GetTwammOrderArgs { order_id: u64 }) -> Option<TwammOrderView>;
+            fn get_account_twamm_orders(GetAccountTwammOrdersArgs { account_id: AccountId })
+                -> Vec<TwammOrderView>;
+
+            // Multisig
+            fn get_multisig(GetMultisigArgs {}) -> Option<MultisigConfig>;
+            fn get_multisig_request(GetMultisigRequestArgs { request_id: u64 })
+                -> Option<MultisigRequestView>;
+            fn get_multisig_requests(GetMultisigRequestsArgs { from_index: u64, limit: u64 })
+                -> Vec<MultisigRequestView>;
+
+            // Owner
+            fn get_owner(GetOwnerArgs {}) -> AccountId;
+            fn get_treasury(GetTreasuryArgs {}) -> AccountId;
+            fn get_protocol_fees(GetProtocolFeesArgs { pool_id: u64 }) -> HashMap<AccountId, U128>;
+            fn get_referral_fee(GetReferralFeeArgs { referral_id: AccountId }) -> Option<u32>;
+            fn get_held_balance(GetHeldBalanceArgs { token_id: AccountId }) -> U128;
+
+            // Pools
+            fn get_pool_id(GetPoolIdArgs {
+                token_a: AccountId,
+                token_b: AccountId,
+                fee_bps: Option<u32>,
+            }) -> Option<u64>;
+            fn get_pool(GetPoolArgs { pool_id: u64 }) -> Option<PoolView>;
+            fn get_pool_info(GetPoolInfoArgs { pool_id: u64 }) -> Option<PoolInfo>;
+            fn get_pools(GetPoolsArgs { from_index: u64, limit: u64 }) -> Vec<PoolInfo>;
+            fn get_pair_pools(GetPairPoolsArgs { token_a: AccountId, token_b: AccountId })
+                -> Vec<PoolView>;
+            fn get_liquidity_providers(GetLiquidityProvidersArgs {
+                pool_id: u64,
+                from_index: u64,
+                limit: u64,
+            }) -> Vec<(AccountId, U128)>;
+            fn get_shares(GetSharesArgs { pool_id: u64, account_id: AccountId }) -> U128;
+            fn get_position(GetPositionArgs { account_id: AccountId, pool_id: u64 })
+                -> Option<PositionView>;
+            fn get_pool_stats(GetPoolStatsArgs { pool_id: u64 }) -> PoolStatsView;
+
+            // Storage
+            fn storage_balance_bounds(StorageBalanceBoundsArgs {}) -> StorageBalanceBounds;
+            fn storage_balance_of(StorageBalanceOfArgs { account_id: AccountId })
+                -> Option<StorageBalance>;
+
+            // Timelock
+            fn get_timelock(GetTimelockArgs {}) -> TimelockConfig;
+            fn get_queued_action(GetQueuedActionArgs { action_id: u64 })
+                -> Option<QueuedActionView>;
+            fn get_queued_actions(GetQueuedActionsArgs { from_index: u64, limit: u64 })
+                -> Vec<QueuedActionView>;
+
+            // Transfers
+            fn get_pending_transfer(GetPendingTransferArgs { transfer_id: u64 })
+                -> Option<PendingTransferView>;
+            fn get_locked_balance(GetLockedBalanceArgs {
+                account_id: AccountId,
+                token_id: AccountId,
+            }) -> U128;
+        }
+    }
+}
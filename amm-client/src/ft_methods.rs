@@ -0,0 +1,54 @@
+use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
+use near_sdk::json_types::U128;
+use near_sdk::AccountId;
+
+use crate::{DEFAULT_GAS, TRANSFER_GAS};
+
+// Methods of the FT contract, its resolve callback is left out
+contract_client! {
+    FtClient {
+        calls {
+            // Init method of the contract, not a constructor of the client
+            #[allow(clippy::new_ret_no_self)]
+            fn new(NewArgs {
+                owner_id: AccountId,
+                total_supply: U128,
+                metadata: FungibleTokenMetadata,
+            }) -> (), deposit = 0, gas = DEFAULT_GAS;
+            fn new_default_meta(NewDefaultMetaArgs { owner_id: AccountId, total_supply: U128 })
+                -> (), deposit = 0, gas = DEFAULT_GAS;
+
+            fn ft_transfer(FtTransferArgs {
+                receiver_id: AccountId,
+                amount: U128,
+                memo: Option<String>,
+            }) -> (), deposit = 1, gas = DEFAULT_GAS;
+            // Resolves to the amount used by the receiver
+            fn ft_transfer_call(FtTransferCallArgs {
+                receiver_id: AccountId,
+                amount: U128,
+                memo: Option<String>,
+                msg: String,
+            }) -> U128, deposit = 1, gas = TRANSFER_GAS;
+
+            // Storage, the deposit of `storage_deposit` is the storage balance to add
+            fn storage_deposit(StorageDepositArgs {
+                account_id: Option<AccountId>,
+                registration_only: Option<bool>,
+            }) -> StorageBalance, deposit = 0, gas = DEFAULT_GAS;
+            fn storage_withdraw(StorageWithdrawArgs { amount: Option<U128> })
+                -> StorageBalance, deposit = 1, gas = DEFAULT_GAS;
+            fn storage_unregister(StorageUnregisterArgs { force: Option<bool> })
+                -> bool, deposit = 1, gas = DEFAULT_GAS;
+        }
+        views {
+            fn ft_total_supply(FtTotalSupplyArgs {}) -> U128;
+            fn ft_balance_of(FtBalanceOfArgs { account_id: AccountId }) -> U128;
+            fn ft_metadata(FtMetadataArgs {}) -> FungibleTokenMetadata;
+            fn storage_balance_bounds(StorageBalanceBoundsArgs {}) -> StorageBalanceBounds;
+            fn storage_balance_of(StorageBalanceOfArgs { account_id: AccountId })
+                -> Option<StorageBalance>;
+        }
+    }
+}
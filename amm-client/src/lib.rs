@@ -0,0 +1,171 @@
+// Typed client of AMM and FT contracts. Builders return the method name, JSON arguments,
+// gas and deposit of the call, so it can be sent by any transport: simulation tests,
+// a backend with an RPC client or another contract with `Promise::function_call`
+use std::marker::PhantomData;
+
+use near_sdk::serde::de::DeserializeOwned;
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json;
+use near_sdk::{AccountId, Balance, Gas};
+
+pub use crate::amm_methods::AmmClient;
+pub use crate::ft_methods::FtClient;
+
+#[macro_use]
+mod macros;
+pub mod amm_methods;
+pub mod ft_methods;
+
+pub const DEFAULT_GAS: Gas = Gas(100_000_000_000_000);
+
+// Calls with transfers or callbacks need gas for the whole chain of receipts
+pub const TRANSFER_GAS: Gas = Gas(300_000_000_000_000);
+
+// Change method call, `T` is the type of its result
+pub struct FunctionCall<T> {
+    pub contract_id: AccountId,
+    pub method_name: &'static str,
+    pub args: Vec<u8>,
+    pub gas: Gas,
+    pub deposit: Balance,
+    result: PhantomData<T>,
+}
+
+impl<T> FunctionCall<T> {
+    pub fn new(contract_id: AccountId, method_name: &'static str, args: &impl Serialize) -> Self {
+        Self {
+            contract_id,
+            method_name,
+            args: serde_json::to_vec(args).expect("Failed to serialize arguments"),
+            gas: DEFAULT_GAS,
+            deposit: 0,
+            result: PhantomData,
+        }
+    }
+
+    pub fn with_gas(mut self, gas: Gas) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    // Methods paying for the storage or wrapping NEAR take more than the default deposit
+    pub fn with_deposit(mut self, deposit: Balance) -> Self {
+        self.deposit = deposit;
+        self
+    }
+
+    pub fn args_json(&self) -> String {
+        String::from_utf8(self.args.clone()).expect("Arguments are not UTF-8")
+    }
+}
+
+impl<T: DeserializeOwned> FunctionCall<T> {
+    // Methods without a result return empty bytes
+    pub fn parse_result(&self, result: &[u8]) -> serde_json::Result<T> {
+        parse_result(result)
+    }
+}
+
+// View method call, `T` is the type of its result
+pub struct ViewCall<T> {
+    pub contract_id: AccountId,
+    pub method_name: &'static str,
+    pub args: Vec<u8>,
+    result: PhantomData<T>,
+}
+
+impl<T> ViewCall<T> {
+    pub fn new(contract_id: AccountId, method_name: &'static str, args: &impl Serialize) -> Self {
+        Self {
+            contract_id,
+            method_name,
+            args: serde_json::to_vec(args).expect("Failed to serialize arguments"),
+            result: PhantomData,
+        }
+    }
+
+    pub fn args_json(&self) -> String {
+        String::from_utf8(self.args.clone()).expect("Arguments are not UTF-8")
+    }
+}
+
+impl<T: DeserializeOwned> ViewCall<T> {
+    pub fn parse_result(&self, result: &[u8]) -> serde_json::Result<T> {
+        parse_result(result)
+    }
+}
+
+fn parse_result<T: DeserializeOwned>(result: &[u8]) -> serde_json::Result<T> {
+    if result.is_empty() {
+        serde_json::from_slice(b"null")
+    } else {
+        serde_json::from_slice(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U128;
+    use near_sdk::serde_json::{self, json, Value};
+
+    use crate::amm_methods::{GetOwnerArgs, SwapArgs};
+    use crate::ft_methods::FtTransferCallArgs;
+    use crate::{AmmClient, FtClient, FunctionCall, TRANSFER_GAS};
+
+    #[test]
+    fn check_swap_args() {
+        let amm = AmmClient {
+            contract_id: "amm".parse().unwrap(),
+        };
+        let call = amm.swap(SwapArgs {
+            buy_token_name: "token_b".parse().unwrap(),
+            sell_token_name: "token_a".parse().unwrap(),
+            sell_amount: U128::from(1_000),
+            min_amount_out: Some(U128::from(900)),
+            referral_id: None,
+            fee_bps: None,
+        });
+        assert_eq!(call.method_name, "swap");
+        assert_eq!(
+            serde_json::from_slice::<Value>(&call.args).unwrap(),
+            json!({
+                "buy_token_name": "token_b",
+                "sell_token_name": "token_a",
+                "sell_amount": "1000",
+                "min_amount_out": "900",
+                "referral_id": null,
+                "fee_bps": null,
+            })
+        );
+        assert_eq!(call.deposit, 0);
+        let view = amm.get_owner(GetOwnerArgs {});
+        assert_eq!(view.args_json(), "{}");
+        let owner: near_sdk::AccountId = view.parse_result(b"\"owner\"").unwrap();
+        assert_eq!(owner.as_str(), "owner");
+    }
+
+    #[test]
+    fn check_ft_transfer_call_args() {
+        let ft = FtClient {
+            contract_id: "token_a".parse().unwrap(),
+        };
+        let call = ft.ft_transfer_call(FtTransferCallArgs {
+            receiver_id: "amm".parse().unwrap(),
+            amount: U128::from(10),
+            memo: None,
+            msg: "".to_string(),
+        });
+        assert_eq!(
+            call.args_json(),
+            r#"{"receiver_id":"amm","amount":"10","memo":null,"msg":""}"#
+        );
+        assert_eq!(call.deposit, 1);
+        assert_eq!(call.gas, TRANSFER_GAS);
+        let used: U128 = call.parse_result(b"\"10\"").unwrap();
+        assert_eq!(used.0, 10);
+
+        // Methods without a result return empty bytes
+        let call: FunctionCall<()> = FunctionCall::new("amm".parse().unwrap(), "vote", &json!({}));
+        assert!(call.parse_result(b"").is_ok());
+    }
+}
@@ -0,0 +1,61 @@
+// Declares argument structs of contract methods and builders of their calls.
+// Field names are the parameter names of the method, so the JSON is exactly
+// what the contract expects
+macro_rules! contract_client {
+    (
+        $client:ident {
+            calls {
+                $(
+                    $(#[$call_meta:meta])*
+                    fn $call:ident($call_args:ident { $($call_field:ident: $call_type:ty),* $(,)? })
+                        -> $call_result:ty, deposit = $deposit:expr, gas = $gas:expr;
+                )*
+            }
+            views {
+                $(
+                    $(#[$view_meta:meta])*
+                    fn $view:ident($view_args:ident { $($view_field:ident: $view_type:ty),* $(,)? })
+                        -> $view_result:ty;
+                )*
+            }
+        }
+    ) => {
+        pub struct $client {
+            pub contract_id: near_sdk::AccountId,
+        }
+
+        $(
+            #[derive(near_sdk::serde::Serialize, near_sdk::serde::Deserialize)]
+            #[serde(crate = "near_sdk::serde")]
+            pub struct $call_args {
+                $(pub $call_field: $call_type,)*
+            }
+        )*
+
+        $(
+            #[derive(near_sdk::serde::Serialize, near_sdk::serde::Deserialize)]
+            #[serde(crate = "near_sdk::serde")]
+            pub struct $view_args {
+                $(pub $view_field: $view_type,)*
+            }
+        )*
+
+        impl $client {
+            $(
+                $(#[$call_meta])*
+                pub fn $call(&self, args: $call_args) -> $crate::FunctionCall<$call_result> {
+                    $crate::FunctionCall::new(self.contract_id.clone(), stringify!($call), &args)
+                        .with_deposit($deposit)
+                        .with_gas($gas)
+                }
+            )*
+
+            $(
+                $(#[$view_meta])*
+                pub fn $view(&self, args: $view_args) -> $crate::ViewCall<$view_result> {
+                    $crate::ViewCall::new(self.contract_id.clone(), stringify!($view), &args)
+                }
+            )*
+        }
+    };
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance};
+
+use crate::*;
+
+// Amount of tokens used by the action
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ActionAmount {
+    Exact(U128),
+    // All tokens received by previous actions of the batch and not used yet
+    AllReceived,
+}
+
+// Action of the batch, it's executed against the deposits of the caller
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde", tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    Swap {
+        sell_token_id: AccountId,
+        buy_token_id: AccountId,
+        sell_amount: ActionAmount,
+        // The swap fails if it buys less
+        min_amount_out: Option<U128>,
+        referral_id: Option<AccountId>,
+        fee_bps: Option<u32>,
+    },
+    AddLiquidity {
+        token_a_id: AccountId,
+        token_a_amount: ActionAmount,
+        token_b_id: AccountId,
+        token_b_amount: ActionAmount,
+        fee_bps: Option<u32>,
+    },
+    // Excludes the shares (all if not specified) from the pool
+    RemoveLiquidity {
+        token_a_id: AccountId,
+        token_b_id: AccountId,
+        shares: Option<U128>,
+        fee_bps: Option<u32>,
+    },
+    // Sends tokens from the deposit to the wallet of the caller
+    Withdraw {
+        token_id: AccountId,
+        amount: ActionAmount,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", tag = "type", rename_all = "snake_case")]
+pub enum ActionResult {
+    Swap(SwapResult),
+    AddLiquidity { shares: U128 },
+    RemoveLiquidity { amounts: HashMap<AccountId, U128> },
+    Withdraw { amount: U128 },
+}
+
+// Tokens received by actions of the batch
+#[derive(Default)]
+struct Received(HashMap<AccountId, Balance>);
+
+impl Received {
+    fn add(&mut self, token_id: &AccountId, amount: Balance) {
+        *self.0.entry(token_id.clone()).or_default() += amount;
+    }
+
+    fn take(&mut self, token_id: &AccountId, amount: &ActionAmount) -> U128 {
+        match amount {
+            ActionAmount::Exact(amount) => *amount,
+            ActionAmount::AllReceived => U128::from(self.0.remove(token_id).unwrap_or(0)),
+        }
+    }
+}
+
+impl AMM {
+    fn internal_execute_action(
+        &mut self,
+        account_id: &AccountId,
+        action: Action,
+        received: &mut Received,
+    ) -> ActionResult {
+        match action {
+            Action::Swap {
+                sell_token_id,
+                buy_token_id,
+                sell_amount,
+                min_amount_out,
+                referral_id,
+                fee_bps,
+            } => {
+                let sell_amount = received.take(&sell_token_id, &sell_amount);
+                let result = self.internal_swap(
+                    account_id,
+                    &buy_token_id,
+                    &sell_token_id,
+                    sell_amount,
+                    min_amount_out,
+                    referral_id.as_ref(),
+                    fee_bps,
+                );
+                received.add(&buy_token_id, result.buy_amount.0);
+                ActionResult::Swap(result)
+            }
+            Action::AddLiquidity {
+                token_a_id,
+                token_a_amount,
+                token_b_id,
+                token_b_amount,
+                fee_bps,
+            } => {
+                let token_a_amount = received.take(&token_a_id, &token_a_amount);
+                let token_b_amount = received.take(&token_b_id, &token_b_amount);
+                let shares = self.internal_add_liquidity(
+                    account_id,
+                    &token_a_id,
+                    token_a_amount,
+                    &token_b_id,
+                    token_b_amount,
+                    fee_bps,
+                );
+                ActionResult::AddLiquidity {
+                    shares: U128::from(shares),
+                }
+            }
+            Action::RemoveLiquidity {
+                token_a_id,
+                token_b_id,
+                shares,
+                fee_bps,
+            } => {
+                let amounts = self.internal_remove_liquidity(
+                    account_id,
+                    &token_a_id,
+                    &token_b_id,
+                    shares.map(|shares| shares.0),
+                    fee_bps,
+                );
+                for (token_id, amount) in amounts.iter() {
+                    received.add(token_id, *amount);
+                }
+                ActionResult::RemoveLiquidity {
+                    amounts: amounts
+                        .into_iter()
+                        .map(|(token_id, amount)| (token_id, U128::from(amount)))
+                        .collect(),
+                }
+            }
+            Action::Withdraw { token_id, amount } => {
+                if !self.tokens.contains_key(&token_id) {
+                    fail(AmmError::TokenNotSupported);
+                }
+                if token_id == near_token_id() {
+                    fail(AmmError::NearWithdrawOnly);
+                }
+                let amount = received.take(&token_id, &amount);
+                self.internal_start_transfer(account_id, &token_id, amount.0);
+                ActionResult::Withdraw { amount }
+            }
+        }
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Executes actions one by one against the deposits of the caller.
+    // If any action fails, the whole batch is reverted.
+    // Batches with withdrawals need 1 yoctoNEAR attached, as `withdraw_tokens`
+    #[payable]
+    pub fn execute(&mut self, actions: Vec<Action>) -> Vec<ActionResult> {
+        if actions.is_empty() {
+            fail(AmmError::EmptyActions)
+        }
+        if actions
+            .iter()
+            .any(|action| matches!(action, Action::Withdraw { .. }))
+        {
+            assert_one_yocto();
+        }
+        let account_id = env::predecessor_account_id();
+        let mut received = Received::default();
+        actions
+            .into_iter()
+            .map(|action| self.internal_execute_action(&account_id, action, &mut received))
+            .collect()
+    }
+}
@@ -0,0 +1,199 @@
+use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, AccountId, Gas, Promise};
+
+use crate::governance::GovernanceConfig;
+use crate::multisig::MultisigConfig;
+use crate::timelock::TimelockConfig;
+use crate::*;
+
+// Change of AMM parameters. Owner methods apply them directly,
+// governance applies them by executed proposals
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde", tag = "type", rename_all = "snake_case")]
+pub enum AdminAction {
+    SetOwner {
+        owner_id: AccountId,
+    },
+    SetTreasury {
+        treasury_id: AccountId,
+    },
+    ListToken {
+        token_id: AccountId,
+        metadata: FungibleTokenMetadata,
+    },
+    AddPool {
+        token_a: AccountId,
+        token_b: AccountId,
+        fee_bps: u32,
+    },
+    // Moves the pool to another fee tier, the pair must not have a pool in it
+    SetFee {
+        pool_id: u64,
+        fee_bps: u32,
+    },
+    SetDynamicFee {
+        pool_id: u64,
+        config: Option<DynamicFeeConfig>,
+    },
+    SetMaxPriceImpact {
+        pool_id: u64,
+        max_price_impact_bps: Option<u32>,
+    },
+    SetProtocolFee {
+        pool_id: u64,
+        protocol_fee_bps: u32,
+    },
+    ClaimProtocolFees {
+        pool_id: u64,
+    },
+    // Creates the farm of the pool or changes its rate
+    SetFarm {
+        pool_id: u64,
+        reward_token_id: AccountId,
+        reward_per_second: U128,
+    },
+    // Sends undistributed rewards of the farm of the pool to the treasury
+    ReclaimFarmRewards {
+        pool_id: u64,
+    },
+    // Sends the excess balance of the token, but not more than `max_amount`, to the treasury
+    Skim {
+        token_id: AccountId,
+        max_amount: U128,
+    },
+    // Adds the excess balance of the pool token, but not more than `max_amount`, to its reserves
+    Sync {
+        pool_id: u64,
+        token_id: AccountId,
+        max_amount: U128,
+    },
+    SetReferral {
+        referral_id: AccountId,
+        referral_fee_bps: u32,
+    },
+    RemoveReferral {
+        referral_id: AccountId,
+    },
+    SetTimelock {
+        config: TimelockConfig,
+    },
+    SetMultisig {
+        config: MultisigConfig,
+    },
+    SetGovernanceConfig {
+        config: GovernanceConfig,
+    },
+    // Deploys the new code of AMM to its account and calls its `migrate`
+    Upgrade {
+        code: Base64VecU8,
+    },
+}
+
+impl AdminAction {
+    // Name of the action for events, payloads like the code of upgrade are too big for logs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AdminAction::SetOwner { .. } => "set_owner",
+            AdminAction::SetTreasury { .. } => "set_treasury",
+            AdminAction::ListToken { .. } => "list_token",
+            AdminAction::AddPool { .. } => "add_pool",
+            AdminAction::SetFee { .. } => "set_fee",
+            AdminAction::SetDynamicFee { .. } => "set_dynamic_fee",
+            AdminAction::SetMaxPriceImpact { .. } => "set_max_price_impact",
+            AdminAction::SetProtocolFee { .. } => "set_protocol_fee",
+            AdminAction::ClaimProtocolFees { .. } => "claim_protocol_fees",
+            AdminAction::SetFarm { .. } => "set_farm",
+            AdminAction::ReclaimFarmRewards { .. } => "reclaim_farm_rewards",
+            AdminAction::Skim { .. } => "skim",
+            AdminAction::Sync { .. } => "sync",
+            AdminAction::SetReferral { .. } => "set_referral",
+            AdminAction::RemoveReferral { .. } => "remove_referral",
+            AdminAction::SetTimelock { .. } => "set_timelock",
+            AdminAction::SetMultisig { .. } => "set_multisig",
+            AdminAction::SetGovernanceConfig { .. } => "set_governance_config",
+            AdminAction::Upgrade { .. } => "upgrade",
+        }
+    }
+}
+
+impl AMM {
+    pub(crate) fn internal_execute_admin_action(&mut self, action: AdminAction) {
+        match action {
+            AdminAction::SetOwner { owner_id } => self.owner_id = owner_id,
+            AdminAction::SetTreasury { treasury_id } => self.treasury_id = treasury_id,
+            AdminAction::ListToken { token_id, metadata } => {
+                self.internal_list_token(token_id, metadata)
+            }
+            AdminAction::AddPool {
+                token_a,
+                token_b,
+                fee_bps,
+            } => {
+                self.internal_add_pool(token_a, token_b, fee_bps);
+            }
+            AdminAction::SetFee { pool_id, fee_bps } => self.internal_set_fee(pool_id, fee_bps),
+            AdminAction::SetDynamicFee { pool_id, config } => {
+                self.internal_set_dynamic_fee(pool_id, config)
+            }
+            AdminAction::SetMaxPriceImpact {
+                pool_id,
+                max_price_impact_bps,
+            } => self.internal_set_max_price_impact(pool_id, max_price_impact_bps),
+            AdminAction::SetProtocolFee {
+                pool_id,
+                protocol_fee_bps,
+            } => self.internal_set_protocol_fee(pool_id, protocol_fee_bps),
+            AdminAction::ClaimProtocolFees { pool_id } => {
+                self.internal_claim_protocol_fees(pool_id)
+            }
+            AdminAction::SetFarm {
+                pool_id,
+                reward_token_id,
+                reward_per_second,
+            } => self.internal_set_farm(pool_id, reward_token_id, reward_per_second.0),
+            AdminAction::ReclaimFarmRewards { pool_id } => {
+                self.internal_reclaim_farm_rewards(pool_id)
+            }
+            AdminAction::Skim {
+                token_id,
+                max_amount,
+            } => self.internal_skim(token_id, max_amount),
+            AdminAction::Sync {
+                pool_id,
+                token_id,
+                max_amount,
+            } => self.internal_sync(pool_id, token_id, max_amount),
+            AdminAction::SetReferral {
+                referral_id,
+                referral_fee_bps,
+            } => self.internal_set_referral(referral_id, referral_fee_bps),
+            AdminAction::RemoveReferral { referral_id } => {
+                self.referrals.remove(&referral_id);
+            }
+            AdminAction::SetTimelock { config } => self.timelock = config,
+            AdminAction::SetMultisig { config } => {
+                config.assert_valid();
+                self.multisig = Some(config);
+            }
+            AdminAction::SetGovernanceConfig { config } => {
+                config.assert_valid(self);
+                self.governance = Some(config);
+            }
+            AdminAction::Upgrade { code } => {
+                // The state is migrated in the batch of the deployment,
+                // so the new code never runs with the old state
+                Promise::new(env::current_account_id())
+                    .deploy_contract(code.into())
+                    .function_call(
+                        "migrate".to_string(),
+                        vec![],
+                        0,
+                        Gas::from(30_000_000_000_000),
+                    );
+            }
+        }
+    }
+}
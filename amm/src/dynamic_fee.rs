@@ -0,0 +1,69 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::Timestamp;
+
+use crate::errors::{fail_with, AmmError};
+use crate::pool::FEE_DIVISOR;
+use crate::utils::NANOS_PER_SEC;
+
+// Settings of the dynamic fee of the pool. The fee grows linearly from `min_fee_bps`
+// to `max_fee_bps` while the volatility grows from zero to `max_volatility_bps`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DynamicFeeConfig {
+    pub min_fee_bps: u32,
+    pub max_fee_bps: u32,
+    pub max_volatility_bps: u32,
+    // Time in seconds for the volatility to decay by half
+    pub decay_period_sec: u32,
+}
+
+impl DynamicFeeConfig {
+    pub fn assert_valid(&self) {
+        if self.min_fee_bps > self.max_fee_bps {
+            fail_with(
+                AmmError::InvalidDynamicFee,
+                "min fee can't be greater than max fee",
+            )
+        }
+        if self.max_fee_bps > FEE_DIVISOR {
+            fail_with(AmmError::BasisPointsTooLarge, "max fee")
+        }
+        if self.max_volatility_bps == 0 || self.decay_period_sec == 0 {
+            fail_with(
+                AmmError::InvalidDynamicFee,
+                "max volatility and decay period must be greater than zero",
+            )
+        }
+    }
+
+    pub fn fee_bps(&self, volatility_bps: u128) -> u32 {
+        let max_volatility = u128::from(self.max_volatility_bps);
+        let volatility = volatility_bps.min(max_volatility);
+        let fee_range = u128::from(self.max_fee_bps - self.min_fee_bps);
+        self.min_fee_bps + (fee_range * volatility / max_volatility) as u32
+    }
+}
+
+// Sum of recent price changes of the pool in basis points, it decays over time
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct Volatility {
+    pub accumulator_bps: u128,
+    pub updated_at: Timestamp,
+}
+
+impl Volatility {
+    // Accumulated volatility at the moment, it falls by half after one decay period
+    // without swaps and keeps falling slower after that
+    pub fn value(&self, config: &DynamicFeeConfig, now: Timestamp) -> u128 {
+        let elapsed_sec = u128::from(now.saturating_sub(self.updated_at) / NANOS_PER_SEC);
+        let decay_period_sec = u128::from(config.decay_period_sec);
+        self.accumulator_bps * decay_period_sec / (decay_period_sec + elapsed_sec)
+    }
+
+    // Adds the price change of the swap to the decayed volatility
+    pub fn observe(&mut self, config: &DynamicFeeConfig, now: Timestamp, price_change_bps: u128) {
+        self.accumulator_bps = self.value(config, now) + price_change_bps;
+        self.updated_at = now;
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+
+use near_sdk::env;
+
+// Errors of AMM. Panic messages start with the stable code of the error, e.g.
+// "E021: Not enough tokens in deposit", so integrators can match on it.
+// Codes must never be reused or changed, new errors get new codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmmError {
+    // Tokens and pools
+    AlreadyInitialized = 1,
+    TokenNotSupported = 2,
+    TokensAreEqual = 3,
+    TokenAlreadyListed = 4,
+    PoolNotFound = 5,
+    PoolAlreadyExists = 6,
+    FeeTierNotSupported = 7,
+    BasisPointsTooLarge = 8,
+    InvalidDynamicFee = 9,
+    IncorrectProportions = 10,
+    LiquidityTooSmall = 11,
+    NotEnoughShares = 12,
+    PoolHasNoLiquidity = 13,
+    PriceImpactTooHigh = 14,
+    BuyAmountTooSmall = 15,
+    ReferrerNotRegistered = 16,
+    ZeroInitialLiquidity = 17,
+
+    // Ledger and storage
+    AccountNotRegistered = 20,
+    NotEnoughDeposit = 21,
+    BalanceOverflow = 22,
+    NotEnoughLockedTokens = 23,
+    NotEnoughStorage = 24,
+    StorageDepositTooSmall = 25,
+    StorageWithdrawTooLarge = 26,
+    UnregisterWithDeposits = 27,
+    UnregisterWithPendingTransfers = 28,
+    UnregisterWithLiquidity = 29,
+    NearWithdrawOnly = 30,
+    ZeroDeposit = 31,
+    TransferNotFound = 32,
+    InvalidMessage = 33,
+    EmptyActions = 34,
+    NearBalanceNotReconciled = 35,
+    BalanceQueryFailed = 36,
+    NearTransferCall = 37,
+
+    // Orders
+    LimitOrderNotFound = 40,
+    InvalidLimitOrder = 41,
+    NotLimitOrderMaker = 42,
+    TwammOrderNotFound = 43,
+    TwammOrderEndNotFound = 44,
+    ZeroBlocks = 45,
+    TwammAmountTooSmall = 46,
+    NotTwammOrderMaker = 47,
+
+    // Farms
+    FarmNotFound = 50,
+    RewardTokenMismatch = 52,
+    ZeroShares = 53,
+    NoStakedShares = 54,
+    NotEnoughStakedShares = 55,
+    SharesLocked = 56,
+    LockDurationNotSupported = 57,
+    LockShortened = 58,
+    LockExpiryNotFound = 59,
+
+    // Owner, timelock and multisig
+    NotOwner = 60,
+    OwnerDisabledByGovernance = 61,
+    MultisigRequired = 62,
+    TimelockRequired = 63,
+    ActionNotFound = 64,
+    ActionNotReady = 65,
+    ActionExpired = 66,
+    NotGuardian = 67,
+    MultisigNotEnabled = 68,
+    NotMultisigMember = 69,
+    InvalidMultisigConfig = 70,
+    RequestNotFound = 71,
+    RequestExpired = 72,
+    RequestNotExpired = 73,
+    AlreadyConfirmed = 74,
+    NotInitialized = 75,
+
+    // Governance
+    GovernanceNotEnabled = 80,
+    InvalidGovernanceConfig = 81,
+    NotEnoughStorageDeposit = 82,
+    ProposalNotFound = 83,
+    NotEnoughVotingPower = 84,
+    NotEnoughVotingPowerToPropose = 85,
+    NoVotingPowerAtSnapshot = 86,
+    AlreadyVoted = 87,
+    VotingIsOver = 88,
+    VotingIsNotOver = 89,
+    ProposalDefeated = 90,
+    ProposalExecuted = 91,
+    ProposalInTimelock = 92,
+    VotingTokenChanged = 93,
+}
+
+impl AmmError {
+    pub fn code(&self) -> String {
+        format!("E{:03}", *self as u32)
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            AmmError::AlreadyInitialized => "Already initialized",
+            AmmError::TokenNotSupported => "Token not supported",
+            AmmError::TokensAreEqual => "Tokens can't be equal",
+            AmmError::TokenAlreadyListed => "Token is already listed",
+            AmmError::PoolNotFound => "Pool not found",
+            AmmError::PoolAlreadyExists => "Pool already exists",
+            AmmError::FeeTierNotSupported => "Fee tier is not supported",
+            AmmError::BasisPointsTooLarge => "Basis points can't be greater than 10000",
+            AmmError::InvalidDynamicFee => "Invalid dynamic fee config",
+            AmmError::IncorrectProportions => {
+                "Incorrect proportions for replenishing the liquidity pool"
+            }
+            AmmError::LiquidityTooSmall => "Liquidity is too small",
+            AmmError::NotEnoughShares => "Not enough shares",
+            AmmError::PoolHasNoLiquidity => "Pool has no liquidity",
+            AmmError::PriceImpactTooHigh => "Price impact is greater than the maximum of the pool",
+            AmmError::BuyAmountTooSmall => "Buy amount is less than the minimum",
+            AmmError::ReferrerNotRegistered => "Referrer is not registered",
+            AmmError::ZeroInitialLiquidity => "First liquidity must have both tokens",
+            AmmError::AccountNotRegistered => "Account is not registered",
+            AmmError::NotEnoughDeposit => "Not enough tokens in deposit",
+            AmmError::BalanceOverflow => "Balance overflow",
+            AmmError::NotEnoughLockedTokens => "Not enough locked tokens",
+            AmmError::NotEnoughStorage => "Not enough storage deposit",
+            AmmError::StorageDepositTooSmall => {
+                "The attached deposit is less than the minimum storage balance"
+            }
+            AmmError::StorageWithdrawTooLarge => {
+                "The amount is greater than the available storage balance"
+            }
+            AmmError::UnregisterWithDeposits => {
+                "Can't unregister the account with the positive deposits without force"
+            }
+            AmmError::UnregisterWithPendingTransfers => {
+                "Can't unregister the account with pending transfers"
+            }
+            AmmError::UnregisterWithLiquidity => {
+                "Can't unregister the account with liquidity or open orders"
+            }
+            AmmError::NearWithdrawOnly => "NEAR can be withdrawn only with withdraw_near",
+            AmmError::ZeroDeposit => "Attached deposit must be greater than zero",
+            AmmError::TransferNotFound => "Transfer not found",
+            AmmError::InvalidMessage => "Invalid message",
+            AmmError::EmptyActions => "Actions can't be empty",
+            AmmError::NearBalanceNotReconciled => "NEAR balance can't be reconciled",
+            AmmError::BalanceQueryFailed => "Failed to get the balance of the token",
+            AmmError::NearTransferCall => "NEAR can't be sent with ft_transfer_call",
+            AmmError::LimitOrderNotFound => "Limit order not found",
+            AmmError::InvalidLimitOrder => "Amount and limit price must be greater than zero",
+            AmmError::NotLimitOrderMaker => "Only maker can cancel the limit order",
+            AmmError::TwammOrderNotFound => "Order not found",
+            AmmError::TwammOrderEndNotFound => "Order end is not found",
+            AmmError::ZeroBlocks => "Number of blocks must be greater than zero",
+            AmmError::TwammAmountTooSmall => "Amount is too small for the number of blocks",
+            AmmError::NotTwammOrderMaker => "Only maker can manage the order",
+            AmmError::FarmNotFound => "Farm not found",
+            AmmError::RewardTokenMismatch => "Farm of the pool has another reward token",
+            AmmError::ZeroShares => "Shares must be greater than zero",
+            AmmError::NoStakedShares => "No staked shares",
+            AmmError::NotEnoughStakedShares => "Not enough staked shares",
+            AmmError::SharesLocked => "Shares are locked",
+            AmmError::LockDurationNotSupported => "Lock duration is not supported",
+            AmmError::LockShortened => "Lock can't be shortened",
+            AmmError::LockExpiryNotFound => "Lock expiry is not found",
+            AmmError::NotOwner => "Only owner can call this method",
+            AmmError::OwnerDisabledByGovernance => "Owner methods are disabled by governance",
+            AmmError::MultisigRequired => "Owner actions must be confirmed by the multisig",
+            AmmError::TimelockRequired => "Owner actions must be queued in the timelock",
+            AmmError::ActionNotFound => "Action not found",
+            AmmError::ActionNotReady => "Action can't be executed yet",
+            AmmError::ActionExpired => "Action is expired",
+            AmmError::NotGuardian => "Only owner or guardians can cancel actions",
+            AmmError::MultisigNotEnabled => "Multisig is not enabled",
+            AmmError::NotMultisigMember => "Only multisig members can call this method",
+            AmmError::InvalidMultisigConfig => "Invalid multisig config",
+            AmmError::RequestNotFound => "Request not found",
+            AmmError::RequestExpired => "Request is expired",
+            AmmError::RequestNotExpired => "Request is not expired",
+            AmmError::AlreadyConfirmed => "Already confirmed",
+            AmmError::NotInitialized => "State is not initialized",
+            AmmError::GovernanceNotEnabled => "Governance is not enabled",
+            AmmError::InvalidGovernanceConfig => "Invalid governance config",
+            AmmError::NotEnoughStorageDeposit => "Not enough deposit for the storage",
+            AmmError::ProposalNotFound => "Proposal not found",
+            AmmError::NotEnoughVotingPower => "Not enough voting power",
+            AmmError::NotEnoughVotingPowerToPropose => {
+                "Not enough voting power to create a proposal"
+            }
+            AmmError::NoVotingPowerAtSnapshot => "No voting power at the snapshot of the proposal",
+            AmmError::AlreadyVoted => "Already voted",
+            AmmError::VotingIsOver => "Voting is over",
+            AmmError::VotingIsNotOver => "Voting is not over",
+            AmmError::ProposalDefeated => "Proposal is defeated",
+            AmmError::ProposalExecuted => "Proposal is already executed",
+            AmmError::ProposalInTimelock => "Proposal is in the timelock",
+            AmmError::VotingTokenChanged => {
+                "Governance token is changed, unlock the previous one first"
+            }
+        }
+    }
+}
+
+impl fmt::Display for AmmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+pub(crate) fn fail(error: AmmError) -> ! {
+    env::panic_str(&error.to_string())
+}
+
+// Fails with details of the particular case after the message of the error
+pub(crate) fn fail_with(error: AmmError, details: impl fmt::Display) -> ! {
+    env::panic_str(&format!("{} ({})", error, details))
+}
+
+pub(crate) trait OrFail<T> {
+    fn or_fail(self, error: AmmError) -> T;
+}
+
+impl<T> OrFail<T> for Option<T> {
+    fn or_fail(self, error: AmmError) -> T {
+        self.unwrap_or_else(|| fail(error))
+    }
+}
@@ -0,0 +1,121 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json;
+use near_sdk::{log, AccountId};
+
+// Events of AMM in NEP-297 format
+const EVENT_STANDARD: &str = "amm";
+const EVENT_VERSION: &str = "1.0.0";
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+enum AmmEventKind<'a> {
+    Swap(&'a [Swap<'a>]),
+    LimitOrderFill(&'a [LimitOrderFill<'a>]),
+    AdminActionQueued(&'a [AdminActionQueued<'a>]),
+    AdminActionExecuted(&'a [AdminActionExecuted<'a>]),
+    AdminActionCancelled(&'a [AdminActionCancelled<'a>]),
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct AmmEvent<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: AmmEventKind<'a>,
+}
+
+fn emit(event: AmmEventKind) {
+    let event = AmmEvent {
+        standard: EVENT_STANDARD,
+        version: EVENT_VERSION,
+        event,
+    };
+    log!("EVENT_JSON:{}", serde_json::to_string(&event).unwrap());
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Swap<'a> {
+    pub account_id: &'a AccountId,
+    pub pool_id: u64,
+    pub sell_token_id: &'a AccountId,
+    pub buy_token_id: &'a AccountId,
+    pub sell_amount: U128,
+    pub buy_amount: U128,
+    pub fee: U128,
+    pub fee_bps: u32,
+    pub protocol_fee: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referral_id: Option<&'a AccountId>,
+    pub referral_fee: U128,
+}
+
+impl Swap<'_> {
+    pub fn emit(self) {
+        emit(AmmEventKind::Swap(&[self]))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LimitOrderFill<'a> {
+    pub order_id: u64,
+    pub account_id: &'a AccountId,
+    pub pool_id: u64,
+    pub sell_token_id: &'a AccountId,
+    pub buy_token_id: &'a AccountId,
+    pub sell_amount: U128,
+    pub buy_amount: U128,
+    pub fee: U128,
+}
+
+impl LimitOrderFill<'_> {
+    pub fn emit(self) {
+        emit(AmmEventKind::LimitOrderFill(&[self]))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminActionQueued<'a> {
+    pub action_id: u64,
+    pub kind: &'a str,
+    // Time in seconds after which the action can be executed
+    pub eta: u64,
+}
+
+impl AdminActionQueued<'_> {
+    pub fn emit(self) {
+        emit(AmmEventKind::AdminActionQueued(&[self]))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminActionExecuted<'a> {
+    pub action_id: u64,
+    pub kind: &'a str,
+}
+
+impl AdminActionExecuted<'_> {
+    pub fn emit(self) {
+        emit(AmmEventKind::AdminActionExecuted(&[self]))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminActionCancelled<'a> {
+    pub action_id: u64,
+    pub kind: &'a str,
+    pub account_id: &'a AccountId,
+}
+
+impl AdminActionCancelled<'_> {
+    pub fn emit(self) {
+        emit(AmmEventKind::AdminActionCancelled(&[self]))
+    }
+}
@@ -0,0 +1,46 @@
+// `ext_contract` adds the account, deposit and gas to the arguments of generated functions
+#![allow(clippy::too_many_arguments)]
+
+use near_sdk::ext_contract;
+use near_sdk::json_types::U128;
+
+use crate::actions::Action;
+
+// Interface of AMM for other contracts, e.g. `ext_amm::swap(..., amm_id, 0, gas)`.
+// Methods act on the deposit of the calling contract, so it has to be registered with
+// `storage_deposit` first.
+//
+// To swap tokens and get the result in one call the contract sends them with
+// `ft_transfer_call` to AMM with the message
+// `{"swap_and_call": {"buy_token_id": ..., "receiver_id": ..., "msg": ...}}`,
+// optional `min_amount_out`, `referral_id` and `fee_bps` work as in `swap`.
+// AMM sends bought tokens with `ft_transfer_call` to `receiver_id` with `msg`,
+// the receiver gets AMM as `sender_id`. Tokens the receiver doesn't use or a failed
+// transfer go to the AMM deposit of the sender, failed swap refunds sold tokens
+#[ext_contract(ext_amm)]
+pub trait AmmInterface {
+    fn swap(
+        &mut self,
+        buy_token_name: AccountId,
+        sell_token_name: AccountId,
+        sell_amount: U128,
+        min_amount_out: Option<U128>,
+        referral_id: Option<AccountId>,
+        fee_bps: Option<u32>,
+    ) -> crate::SwapResult;
+    // Needs 1 yoctoNEAR attached if there are withdrawals
+    fn execute(&mut self, actions: Vec<Action>) -> Vec<crate::actions::ActionResult>;
+
+    // Needs 1 yoctoNEAR attached, resolves to false if the transfer failed and tokens are
+    // back in the deposit
+    fn withdraw_tokens(&mut self, token_name: AccountId, amount: U128) -> bool;
+
+    fn get_deposit(&self, account_id: AccountId, token_id: AccountId) -> U128;
+    fn get_pool_id(
+        &self,
+        token_a: AccountId,
+        token_b: AccountId,
+        fee_bps: Option<u32>,
+    ) -> Option<u64>;
+    fn get_pool(&self, pool_id: u64) -> Option<crate::views::PoolView>;
+}
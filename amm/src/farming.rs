@@ -0,0 +1,508 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::TreeMap;
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Balance, Gas, StorageUsage};
+
+use crate::positions::Position;
+use crate::utils::{now_sec, U256};
+use crate::*;
+
+// Precision of rewards accumulated per staked share
+const REWARD_PRECISION: u128 = 1_000_000_000_000_000_000_000_000;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+const BOOST_DIVISOR: u32 = 100;
+
+// Supported lock durations in seconds and boosts of the rewards for them in percent
+pub const LOCK_BOOSTS: [(u64, u32); 5] = [
+    (0, 100),
+    (7 * SECS_PER_DAY, 110),
+    (30 * SECS_PER_DAY, 125),
+    (90 * SECS_PER_DAY, 150),
+    (365 * SECS_PER_DAY, 200),
+];
+
+// Rewards of LPs of the pool. The owner sets the reward token and the rate, reward tokens
+// are added by `ft_transfer_call` and distributed to the weights of stakes at a constant rate until they run out.
+// The weight is staked shares multiplied by the boost of the lock. Rewards aren't spent
+// while nobody stakes, they stay undistributed until stakes appear or the owner reclaims them
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Farm {
+    pub reward_token_id: AccountId,
+    pub reward_per_second: Balance,
+    // Rewards left for the distribution
+    pub undistributed: Balance,
+    pub total_staked: Balance,
+    pub total_weight: Balance,
+    // Rewards distributed per unit of weight, multiplied by `REWARD_PRECISION`
+    pub reward_per_weight: U256,
+    // Time in seconds of the last distribution
+    pub updated_at: u64,
+    // Locks of stakes ending at the time
+    pub expiries: TreeMap<u64, LockExpiry>,
+    // Expiries passed by the distribution and not saved yet
+    #[borsh_skip]
+    passed: Vec<(u64, LockExpiry)>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Default)]
+pub struct LockExpiry {
+    // Weight added by boosts of the locks
+    pub boost_weight: Balance,
+    // Number of stakes which still need the expiry to accrue rewards
+    pub stakes: u32,
+    // Value of `reward_per_weight` at the time, it's set when the time is passed
+    pub reward_per_weight: U256,
+}
+
+impl Farm {
+    fn new(pool_id: u64, reward_token_id: AccountId, now: u64) -> Self {
+        Self {
+            reward_token_id,
+            reward_per_second: 0,
+            undistributed: 0,
+            total_staked: 0,
+            total_weight: 0,
+            reward_per_weight: U256::zero(),
+            updated_at: now,
+            expiries: TreeMap::new(format!("e{}", pool_id).into_bytes()),
+            passed: vec![],
+        }
+    }
+
+    // Distributes rewards for the time passed since the last distribution.
+    // Time is distributed by segments between lock expiries, weights of expired locks
+    // lose their boosts at the end of segment. Nothing is distributed while nobody stakes
+    fn distribute(&mut self, now: u64) {
+        while self.updated_at < now {
+            let segment_end = self
+                .expiries
+                .higher(&self.updated_at)
+                .unwrap_or(now)
+                .min(now);
+            if self.total_weight > 0 {
+                let elapsed = Balance::from(segment_end - self.updated_at);
+                let reward = (elapsed * self.reward_per_second).min(self.undistributed);
+                self.undistributed -= reward;
+                self.reward_per_weight += U256::from(reward) * U256::from(REWARD_PRECISION)
+                    / U256::from(self.total_weight);
+            }
+            self.updated_at = segment_end;
+            if let Some(mut expiry) = self.expiries.get(&segment_end) {
+                self.total_weight -= expiry.boost_weight;
+                expiry.reward_per_weight = self.reward_per_weight;
+                self.passed.push((segment_end, expiry));
+            }
+        }
+    }
+
+    // Distributes rewards and saves passed expiries
+    fn update(&mut self, now: u64) {
+        self.distribute(now);
+        for (time, expiry) in std::mem::take(&mut self.passed) {
+            self.expiries.insert(&time, &expiry);
+        }
+    }
+
+    fn expiry(&self, time: u64) -> LockExpiry {
+        self.passed
+            .iter()
+            .find(|(passed_time, _)| *passed_time == time)
+            .map(|(_, expiry)| expiry.clone())
+            .or_else(|| self.expiries.get(&time))
+            .or_fail(AmmError::LockExpiryNotFound)
+    }
+
+    // Removes the stake from the expiry, the expiry is removed with the last stake
+    fn release_expiry(&mut self, time: u64, boost_weight: Balance) {
+        let mut expiry = self.expiry(time);
+        expiry.boost_weight -= boost_weight;
+        expiry.stakes -= 1;
+        if expiry.stakes == 0 {
+            self.expiries.remove(&time);
+        } else {
+            self.expiries.insert(&time, &expiry);
+        }
+    }
+
+    // Accrues rewards of the stake, the passed expiry of its lock is released
+    fn accrue(&mut self, stake: &mut Stake) {
+        if let Some(lock_until) = stake.accrue(self) {
+            self.release_expiry(lock_until, 0);
+        }
+    }
+
+    // Adds shares to the stake and locks the whole stake until the time with the boost.
+    // Current lock of the stake is replaced, rewards must be accrued before
+    fn lock(&mut self, stake: &mut Stake, shares: Balance, lock_until: u64, boost: u32) {
+        if stake.lock_until > 0 {
+            self.release_expiry(stake.lock_until, stake.weight - stake.shares);
+        }
+        self.total_staked += shares;
+        self.total_weight -= stake.weight;
+        stake.shares += shares;
+        stake.weight = stake.shares * Balance::from(boost) / Balance::from(BOOST_DIVISOR);
+        stake.lock_until = lock_until;
+        self.total_weight += stake.weight;
+        if lock_until > 0 {
+            let mut expiry = self.expiries.get(&lock_until).unwrap_or_default();
+            expiry.boost_weight += stake.weight - stake.shares;
+            expiry.stakes += 1;
+            self.expiries.insert(&lock_until, &expiry);
+        }
+    }
+}
+
+// Shares of the account staked in the farm
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Stake {
+    pub shares: Balance,
+    // Shares multiplied by the boost of the lock, it's equal to shares without a lock
+    pub weight: Balance,
+    // Time in seconds until shares are locked, 0 if they aren't
+    pub lock_until: u64,
+    // Value of `reward_per_weight` when rewards of the stake were accrued last time
+    pub reward_per_weight_paid: U256,
+    // Rewards accrued and not claimed yet
+    pub unclaimed: Balance,
+    // LP position entry of staked shares, it returns to the position with unstaked shares
+    pub entry: Position,
+    // Bytes of the stake records charged from the account storage balance
+    pub storage_usage: StorageUsage,
+}
+
+impl Stake {
+    fn accrue_until(&mut self, reward_per_weight: U256) {
+        self.unclaimed += (U256::from(self.weight)
+            * (reward_per_weight - self.reward_per_weight_paid)
+            / U256::from(REWARD_PRECISION))
+        .as_u128();
+        self.reward_per_weight_paid = reward_per_weight;
+    }
+
+    // Accrues rewards with the boosted weight until the end of the lock and with
+    // shares after it. Returns the end of the lock if it has passed
+    fn accrue(&mut self, farm: &Farm) -> Option<u64> {
+        let mut expired = None;
+        if self.lock_until > 0 && self.lock_until <= farm.updated_at {
+            self.accrue_until(farm.expiry(self.lock_until).reward_per_weight);
+            self.weight = self.shares;
+            expired = Some(self.lock_until);
+            self.lock_until = 0;
+        }
+        self.accrue_until(farm.reward_per_weight);
+        expired
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FarmView {
+    pub pool_id: u64,
+    pub reward_token_id: AccountId,
+    pub reward_per_second: U128,
+    pub undistributed: U128,
+    pub total_staked: U128,
+    pub total_weight: U128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakeView {
+    pub shares: U128,
+    pub weight: U128,
+    pub lock_until: u64,
+    pub pending_rewards: U128,
+}
+
+fn lock_boost(lock_duration: u64) -> u32 {
+    LOCK_BOOSTS
+        .iter()
+        .find(|(duration, _)| *duration == lock_duration)
+        .map(|(_, boost)| *boost)
+        .or_fail(AmmError::LockDurationNotSupported)
+}
+
+impl AMM {
+    fn internal_unwrap_farm(&self, pool_id: u64) -> Farm {
+        let mut farm = self.farms.get(&pool_id).or_fail(AmmError::FarmNotFound);
+        farm.update(now_sec());
+        farm
+    }
+
+    // Creates the farm of the pool or changes its rate, the reward token of the farm can't be changed
+    pub(crate) fn internal_set_farm(
+        &mut self,
+        pool_id: u64,
+        reward_token_id: AccountId,
+        reward_per_second: Balance,
+    ) {
+        if self.pools.get(pool_id).is_none() {
+            fail(AmmError::PoolNotFound)
+        }
+        if !self.tokens.contains_key(&reward_token_id) || reward_token_id == near_token_id() {
+            fail(AmmError::TokenNotSupported)
+        }
+        let mut farm = match self.farms.get(&pool_id) {
+            Some(mut farm) => {
+                if farm.reward_token_id != reward_token_id {
+                    fail(AmmError::RewardTokenMismatch)
+                }
+                farm.update(now_sec());
+                farm
+            }
+            None => Farm::new(pool_id, reward_token_id, now_sec()),
+        };
+        farm.reward_per_second = reward_per_second;
+        self.farms.insert(&pool_id, &farm);
+    }
+
+    // Adds rewards to the farm of the pool, anyone can fund an existing farm
+    pub(crate) fn internal_fund_farm(
+        &mut self,
+        reward_token_id: &AccountId,
+        amount: Balance,
+        pool_id: u64,
+    ) {
+        let mut farm = self.internal_unwrap_farm(pool_id);
+        if &farm.reward_token_id != reward_token_id {
+            fail(AmmError::RewardTokenMismatch)
+        }
+        farm.undistributed += amount;
+        self.farms.insert(&pool_id, &farm);
+        log!(
+            "Farm of pool {} is funded with {} of {}",
+            pool_id,
+            amount,
+            reward_token_id
+        );
+    }
+
+    // Sends undistributed rewards of the farm to the treasury and stops the distribution,
+    // rewards already distributed stay claimable by stakes. A failed transfer leaves
+    // the tokens in the excess balance of AMM, which can be skimmed
+    pub(crate) fn internal_reclaim_farm_rewards(&mut self, pool_id: u64) {
+        let mut farm = self.internal_unwrap_farm(pool_id);
+        let amount = farm.undistributed;
+        farm.undistributed = 0;
+        self.farms.insert(&pool_id, &farm);
+        if amount == 0 {
+            return;
+        }
+        self.internal_remove_held(&farm.reward_token_id, amount);
+        log!(
+            "Reclaim {} of {} undistributed rewards of farm {}",
+            amount,
+            farm.reward_token_id,
+            pool_id
+        );
+        ext_ft::ft_transfer(
+            self.treasury_id.clone(),
+            U128::from(amount),
+            Some("Farm rewards".to_string()),
+            farm.reward_token_id,
+            1,
+            Gas::from(5_000_000_000_000),
+        );
+    }
+
+    // Credits rewards of the stake to the deposits of the account
+    fn internal_claim_rewards(
+        &mut self,
+        account_id: &AccountId,
+        farm: &mut Farm,
+        stake: &mut Stake,
+    ) -> Balance {
+        farm.accrue(stake);
+        let reward = stake.unclaimed;
+        if reward > 0 {
+            stake.unclaimed = 0;
+            self.internal_deposit(account_id, &farm.reward_token_id, reward);
+        }
+        reward
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Moves LP shares of the pool to the farm, staked shares can't be excluded from the pool.
+    // Shares locked for one of `LOCK_BOOSTS` durations earn boosted rewards and can't be
+    // unstaked until the end of the lock. The lock applies to all staked shares of the account
+    // and can be extended only
+    pub fn stake_shares(&mut self, pool_id: u64, shares: U128, lock_duration: Option<u64>) {
+        if shares.0 == 0 {
+            fail(AmmError::ZeroShares)
+        }
+        let lock_duration = lock_duration.unwrap_or(0);
+        let boost = lock_boost(lock_duration);
+        let account_id = env::predecessor_account_id();
+        let mut farm = self.internal_unwrap_farm(pool_id);
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        let account_shares = pool.shares_of(&account_id);
+        if shares.0 > account_shares {
+            fail(AmmError::NotEnoughShares)
+        }
+        let entry = pool
+            .positions
+            .get(&account_id)
+            .unwrap_or_else(|| pool.position_per_share());
+        pool.shares
+            .insert(&account_id, &(account_shares - shares.0));
+        self.internal_save_pool(pool_id, &pool);
+
+        let key = (pool_id, account_id.clone());
+        let initial_storage_usage = env::storage_usage();
+        let mut stake = match self.stakes.get(&key) {
+            Some(mut stake) => {
+                farm.accrue(&mut stake);
+                stake
+            }
+            None => Stake {
+                shares: 0,
+                weight: 0,
+                lock_until: 0,
+                reward_per_weight_paid: farm.reward_per_weight,
+                unclaimed: 0,
+                entry: entry.clone(),
+                storage_usage: 0,
+            },
+        };
+        stake.entry = stake.entry.merge(stake.shares, &entry, shares.0);
+        let lock_until = if lock_duration > 0 {
+            farm.updated_at + lock_duration
+        } else {
+            0
+        };
+        if stake.lock_until > 0 && lock_until < stake.lock_until {
+            fail(AmmError::LockShortened)
+        }
+        farm.lock(&mut stake, shares.0, lock_until, boost);
+        self.stakes.insert(&key, &stake);
+
+        // The stake record and a new lock expiry are charged from the storage balance of the account
+        if env::storage_usage() > initial_storage_usage {
+            stake.storage_usage += env::storage_usage() - initial_storage_usage;
+            self.stakes.insert(&key, &stake);
+            let mut account = self.internal_unwrap_account(&account_id);
+            account.storage_used += env::storage_usage() - initial_storage_usage;
+            self.internal_save_account(&account_id, &account);
+        }
+        self.farms.insert(&pool_id, &farm);
+    }
+
+    // Returns staked shares (all if not specified) to the pool and claims rewards.
+    // Locked shares can't be unstaked
+    pub fn unstake_shares(&mut self, pool_id: u64, shares: Option<U128>) -> U128 {
+        let account_id = env::predecessor_account_id();
+        let mut farm = self.internal_unwrap_farm(pool_id);
+        let key = (pool_id, account_id.clone());
+        let mut stake = self.stakes.get(&key).or_fail(AmmError::NoStakedShares);
+        let shares = shares.map(|shares| shares.0).unwrap_or(stake.shares);
+        if shares > stake.shares {
+            fail(AmmError::NotEnoughStakedShares)
+        }
+        let reward = self.internal_claim_rewards(&account_id, &mut farm, &mut stake);
+        if stake.lock_until > 0 {
+            fail(AmmError::SharesLocked)
+        }
+        stake.shares -= shares;
+        stake.weight -= shares;
+        farm.total_staked -= shares;
+        farm.total_weight -= shares;
+        self.farms.insert(&pool_id, &farm);
+
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        self.internal_register_shares(&mut pool, &account_id);
+        pool.shares
+            .insert(&account_id, &(pool.shares_of(&account_id) + shares));
+        pool.update_position(&account_id, shares, &stake.entry);
+        self.internal_save_pool(pool_id, &pool);
+
+        if stake.shares == 0 {
+            self.stakes.remove(&key);
+            let mut account = self.internal_unwrap_account(&account_id);
+            account.storage_used -= stake.storage_usage;
+            self.internal_save_account(&account_id, &account);
+        } else {
+            self.stakes.insert(&key, &stake);
+        }
+        U128::from(reward)
+    }
+
+    // Credits accrued rewards of the farm to the deposits
+    pub fn claim_rewards(&mut self, pool_id: u64) -> U128 {
+        let account_id = env::predecessor_account_id();
+        let mut farm = self.internal_unwrap_farm(pool_id);
+        let key = (pool_id, account_id.clone());
+        let mut stake = self.stakes.get(&key).or_fail(AmmError::NoStakedShares);
+        let reward = self.internal_claim_rewards(&account_id, &mut farm, &mut stake);
+        self.stakes.insert(&key, &stake);
+        self.farms.insert(&pool_id, &farm);
+        U128::from(reward)
+    }
+
+    // Creates the farm of the pool with the reward token or changes its rate per second,
+    // rewards are added with the `fund_farm` message of `ft_transfer_call`
+    #[payable]
+    pub fn set_farm(&mut self, pool_id: u64, reward_token_id: AccountId, reward_per_second: U128) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::SetFarm {
+            pool_id,
+            reward_token_id,
+            reward_per_second,
+        });
+    }
+
+    // Sends undistributed rewards of the farm to the treasury, e.g. left after all LPs unstaked
+    #[payable]
+    pub fn reclaim_farm_rewards(&mut self, pool_id: u64) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::ReclaimFarmRewards { pool_id });
+    }
+
+    pub fn get_farm(&self, pool_id: u64) -> Option<FarmView> {
+        self.farms.get(&pool_id).map(|mut farm| {
+            farm.distribute(now_sec());
+            FarmView {
+                pool_id,
+                reward_token_id: farm.reward_token_id,
+                reward_per_second: U128::from(farm.reward_per_second),
+                undistributed: U128::from(farm.undistributed),
+                total_staked: U128::from(farm.total_staked),
+                total_weight: U128::from(farm.total_weight),
+            }
+        })
+    }
+
+    pub fn get_staked_shares(&self, pool_id: u64, account_id: AccountId) -> U128 {
+        U128::from(
+            self.stakes
+                .get(&(pool_id, account_id))
+                .map(|stake| stake.shares)
+                .unwrap_or(0),
+        )
+    }
+
+    // Stake of the account with its weight and rewards it can claim at the moment
+    pub fn get_stake(&self, pool_id: u64, account_id: AccountId) -> Option<StakeView> {
+        let mut farm = self.farms.get(&pool_id)?;
+        let mut stake = self.stakes.get(&(pool_id, account_id))?;
+        farm.distribute(now_sec());
+        stake.accrue(&farm);
+        Some(StakeView {
+            shares: U128::from(stake.shares),
+            weight: U128::from(stake.weight),
+            lock_until: stake.lock_until,
+            pending_rewards: U128::from(stake.unclaimed),
+        })
+    }
+
+    // Rewards the account can claim at the moment
+    pub fn get_pending_rewards(&self, pool_id: u64, account_id: AccountId) -> U128 {
+        self.get_stake(pool_id, account_id)
+            .map(|stake| stake.pending_rewards)
+            .unwrap_or_else(|| U128::from(0))
+    }
+}
@@ -0,0 +1,416 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Balance, BlockHeight, StorageUsage};
+
+use crate::admin::AdminAction;
+use crate::pool::FEE_DIVISOR;
+use crate::utils::{charge_storage, now_sec};
+use crate::*;
+
+// Once it's set, parameters of AMM are changed only by proposals voted
+// with the governance token locked in AMM
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GovernanceConfig {
+    // Listed token giving voting power when locked
+    pub token_id: AccountId,
+    // Voting power needed to create a proposal
+    pub proposal_threshold: U128,
+    pub voting_period_sec: u64,
+    // Minimum of votes for the proposal in basis points of the total voting power
+    pub quorum_bps: u32,
+    // Delay between the end of voting and the execution of the passed proposal
+    pub timelock_sec: u64,
+}
+
+impl GovernanceConfig {
+    pub fn assert_valid(&self, amm: &AMM) {
+        if !amm.tokens.contains_key(&self.token_id) {
+            fail(AmmError::TokenNotSupported)
+        }
+        if self.voting_period_sec == 0 {
+            fail_with(
+                AmmError::InvalidGovernanceConfig,
+                "voting period must be greater than zero",
+            )
+        }
+        if self.quorum_bps > FEE_DIVISOR {
+            fail_with(AmmError::BasisPointsTooLarge, "quorum")
+        }
+    }
+}
+
+// Locked governance tokens of the account by the block of the change
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct VotingPower {
+    // Token locked by the account, it's returned on unlock even if
+    // the governance token is changed since then
+    pub token_id: AccountId,
+    pub checkpoints: Vec<(BlockHeight, Balance)>,
+    // Bytes of the record charged from the account storage balance
+    pub storage_usage: StorageUsage,
+}
+
+impl VotingPower {
+    fn current(&self) -> Balance {
+        self.checkpoints
+            .last()
+            .map(|(_, power)| *power)
+            .unwrap_or(0)
+    }
+
+    // Voting power at the end of the last block before the given one
+    fn before(&self, block: BlockHeight) -> Balance {
+        let index = self
+            .checkpoints
+            .partition_point(|(checkpoint_block, _)| *checkpoint_block < block);
+        if index == 0 {
+            0
+        } else {
+            self.checkpoints[index - 1].1
+        }
+    }
+}
+
+// Sum of voting powers of all accounts locking the token. The value before the current
+// block is kept for the quorum of proposals created in this block
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct TotalVotingPower {
+    pub current: Balance,
+    pub changed_at: BlockHeight,
+    pub before_change: Balance,
+}
+
+impl TotalVotingPower {
+    fn set(&mut self, power: Balance) {
+        let block = env::block_height();
+        if self.changed_at != block {
+            self.before_change = self.current;
+            self.changed_at = block;
+        }
+        self.current = power;
+    }
+
+    fn before(&self, block: BlockHeight) -> Balance {
+        if self.changed_at < block {
+            self.current
+        } else {
+            self.before_change
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Proposal {
+    pub proposer_id: AccountId,
+    pub description: String,
+    pub action: AdminAction,
+    // Votes are weighted by voting power before the block of the proposal
+    pub snapshot_block: BlockHeight,
+    pub total_voting_power: Balance,
+    // Time in seconds
+    pub voting_ends_at: u64,
+    pub votes_for: Balance,
+    pub votes_against: Balance,
+    pub executed: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ProposalStatus {
+    Active,
+    Defeated,
+    // Passed and can be executed after the timelock
+    Succeeded,
+    Executed,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalView {
+    pub proposal_id: u64,
+    pub proposer_id: AccountId,
+    pub description: String,
+    pub action: AdminAction,
+    pub snapshot_block: BlockHeight,
+    pub total_voting_power: U128,
+    pub voting_ends_at: u64,
+    pub votes_for: U128,
+    pub votes_against: U128,
+    pub status: ProposalStatus,
+}
+
+impl AMM {
+    fn internal_unwrap_governance(&self) -> GovernanceConfig {
+        self.governance
+            .clone()
+            .or_fail(AmmError::GovernanceNotEnabled)
+    }
+
+    fn internal_unwrap_proposal(&self, proposal_id: u64) -> Proposal {
+        self.proposals
+            .get(proposal_id)
+            .or_fail(AmmError::ProposalNotFound)
+    }
+
+    fn internal_proposal_status(&self, proposal: &Proposal) -> ProposalStatus {
+        let config = self.internal_unwrap_governance();
+        let quorum = proposal.total_voting_power * Balance::from(config.quorum_bps)
+            / Balance::from(FEE_DIVISOR);
+        if proposal.executed {
+            ProposalStatus::Executed
+        } else if now_sec() < proposal.voting_ends_at {
+            ProposalStatus::Active
+        } else if proposal.votes_for + proposal.votes_against < quorum
+            || proposal.votes_for <= proposal.votes_against
+        {
+            ProposalStatus::Defeated
+        } else {
+            ProposalStatus::Succeeded
+        }
+    }
+
+    // Voting power of the account in the current governance token,
+    // tokens locked before the change of the token don't count
+    fn internal_governance_voting_power(
+        &self,
+        config: &GovernanceConfig,
+        account_id: &AccountId,
+    ) -> Option<VotingPower> {
+        self.voting_powers
+            .get(account_id)
+            .filter(|voting_power| voting_power.token_id == config.token_id)
+    }
+
+    // Moves governance tokens between the deposit and the voting power of the account.
+    // The history of voting power is charged from the storage balance of the account
+    fn internal_set_voting_power(
+        &mut self,
+        account_id: &AccountId,
+        account: &mut Account,
+        token_id: &AccountId,
+        power: Balance,
+    ) {
+        let mut voting_power = self
+            .voting_powers
+            .get(account_id)
+            .unwrap_or_else(|| VotingPower {
+                token_id: token_id.clone(),
+                checkpoints: vec![],
+                storage_usage: 0,
+            });
+        let mut total = self
+            .total_voting_powers
+            .get(&voting_power.token_id)
+            .unwrap_or_default();
+        total.set(total.current - voting_power.current() + power);
+        self.total_voting_powers
+            .insert(&voting_power.token_id, &total);
+        if power == 0 {
+            // Proposals with a snapshot before this block lose the votes of the account
+            self.voting_powers.remove(account_id);
+            account.storage_used -= voting_power.storage_usage;
+            return;
+        }
+        let block = env::block_height();
+        match voting_power.checkpoints.last_mut() {
+            Some((last_block, last_power)) if *last_block == block => *last_power = power,
+            _ => voting_power.checkpoints.push((block, power)),
+        }
+        let initial_storage_usage = env::storage_usage();
+        self.voting_powers.insert(account_id, &voting_power);
+        if env::storage_usage() > initial_storage_usage {
+            let storage_usage = env::storage_usage() - initial_storage_usage;
+            voting_power.storage_usage += storage_usage;
+            self.voting_powers.insert(account_id, &voting_power);
+            account.storage_used += storage_usage;
+        }
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Hands parameters of AMM over to governance, owner methods are disabled after it
+    #[payable]
+    pub fn enable_governance(&mut self, config: GovernanceConfig) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::SetGovernanceConfig { config });
+    }
+
+    pub fn get_governance(&self) -> Option<GovernanceConfig> {
+        self.governance.clone()
+    }
+
+    // Moves governance tokens from the deposit to the voting power. Tokens locked
+    // before the change of the governance token must be unlocked first
+    pub fn lock_voting_tokens(&mut self, amount: U128) {
+        let config = self.internal_unwrap_governance();
+        let account_id = env::predecessor_account_id();
+        if let Some(voting_power) = self.voting_powers.get(&account_id) {
+            if voting_power.token_id != config.token_id {
+                fail(AmmError::VotingTokenChanged)
+            }
+        }
+        let mut account = self.internal_unwrap_account(&account_id);
+        account.withdraw(&config.token_id, amount.0);
+        let power = self.get_voting_power(account_id.clone()).0 + amount.0;
+        self.internal_set_voting_power(&account_id, &mut account, &config.token_id, power);
+        self.internal_save_account(&account_id, &account);
+    }
+
+    // Returns locked tokens from the voting power to the deposit.
+    // Votes already cast are not changed
+    pub fn unlock_voting_tokens(&mut self, amount: U128) {
+        let account_id = env::predecessor_account_id();
+        let voting_power = self
+            .voting_powers
+            .get(&account_id)
+            .or_fail(AmmError::NotEnoughVotingPower);
+        let power = voting_power.current();
+        if amount.0 > power {
+            fail(AmmError::NotEnoughVotingPower)
+        }
+        let mut account = self.internal_unwrap_account(&account_id);
+        account.deposit(&voting_power.token_id, amount.0);
+        self.internal_set_voting_power(
+            &account_id,
+            &mut account,
+            &voting_power.token_id,
+            power - amount.0,
+        );
+        self.internal_save_account(&account_id, &account);
+    }
+
+    // Creates the proposal to apply the action, the storage of the proposal is paid
+    // by the attached deposit
+    #[payable]
+    pub fn create_proposal(&mut self, description: String, action: AdminAction) -> u64 {
+        let config = self.internal_unwrap_governance();
+        let proposer_id = env::predecessor_account_id();
+        let power = self
+            .internal_governance_voting_power(&config, &proposer_id)
+            .map(|voting_power| voting_power.current())
+            .unwrap_or(0);
+        if power < config.proposal_threshold.0 {
+            fail(AmmError::NotEnoughVotingPowerToPropose)
+        }
+        let initial_storage_usage = env::storage_usage();
+        let snapshot_block = env::block_height();
+        let proposal = Proposal {
+            proposer_id,
+            description,
+            action,
+            snapshot_block,
+            total_voting_power: self
+                .total_voting_powers
+                .get(&config.token_id)
+                .map(|total| total.before(snapshot_block))
+                .unwrap_or(0),
+            voting_ends_at: now_sec() + config.voting_period_sec,
+            votes_for: 0,
+            votes_against: 0,
+            executed: false,
+        };
+        self.proposals.push(&proposal);
+        charge_storage(initial_storage_usage);
+        self.proposals.len() - 1
+    }
+
+    // Votes with the voting power at the snapshot of the proposal,
+    // the storage of the vote is paid by the attached deposit
+    #[payable]
+    pub fn vote(&mut self, proposal_id: u64, support: bool) {
+        let config = self.internal_unwrap_governance();
+        let mut proposal = self.internal_unwrap_proposal(proposal_id);
+        if self.internal_proposal_status(&proposal) != ProposalStatus::Active {
+            fail(AmmError::VotingIsOver)
+        }
+        let account_id = env::predecessor_account_id();
+        let power = self
+            .internal_governance_voting_power(&config, &account_id)
+            .map(|voting_power| voting_power.before(proposal.snapshot_block))
+            .unwrap_or(0);
+        if power == 0 {
+            fail(AmmError::NoVotingPowerAtSnapshot)
+        }
+        let initial_storage_usage = env::storage_usage();
+        if self
+            .votes
+            .insert(&(proposal_id, account_id), &support)
+            .is_some()
+        {
+            fail(AmmError::AlreadyVoted)
+        }
+        if support {
+            proposal.votes_for += power;
+        } else {
+            proposal.votes_against += power;
+        }
+        self.proposals.replace(proposal_id, &proposal);
+        charge_storage(initial_storage_usage);
+    }
+
+    // Applies the action of the passed proposal after the timelock, anyone can call it
+    pub fn execute_proposal(&mut self, proposal_id: u64) {
+        let config = self.internal_unwrap_governance();
+        let mut proposal = self.internal_unwrap_proposal(proposal_id);
+        match self.internal_proposal_status(&proposal) {
+            ProposalStatus::Succeeded => {}
+            ProposalStatus::Active => fail(AmmError::VotingIsNotOver),
+            ProposalStatus::Defeated => fail(AmmError::ProposalDefeated),
+            ProposalStatus::Executed => fail(AmmError::ProposalExecuted),
+        }
+        if now_sec() < proposal.voting_ends_at + config.timelock_sec {
+            fail(AmmError::ProposalInTimelock)
+        }
+        proposal.executed = true;
+        self.proposals.replace(proposal_id, &proposal);
+        log!("Execute proposal {}", proposal_id);
+        self.internal_execute_admin_action(proposal.action);
+    }
+
+    // Locked tokens of the account, they give votes only in the current governance token
+    pub fn get_voting_power(&self, account_id: AccountId) -> U128 {
+        U128::from(
+            self.voting_powers
+                .get(&account_id)
+                .map(|voting_power| voting_power.current())
+                .unwrap_or(0),
+        )
+    }
+
+    // Sum of voting powers in the current governance token
+    pub fn get_total_voting_power(&self) -> U128 {
+        U128::from(
+            self.governance
+                .as_ref()
+                .and_then(|config| self.total_voting_powers.get(&config.token_id))
+                .map(|total| total.current)
+                .unwrap_or(0),
+        )
+    }
+
+    pub fn get_proposal(&self, proposal_id: u64) -> Option<ProposalView> {
+        self.proposals
+            .get(proposal_id)
+            .map(|proposal| ProposalView {
+                proposal_id,
+                status: self.internal_proposal_status(&proposal),
+                proposer_id: proposal.proposer_id,
+                description: proposal.description,
+                action: proposal.action,
+                snapshot_block: proposal.snapshot_block,
+                total_voting_power: U128::from(proposal.total_voting_power),
+                voting_ends_at: proposal.voting_ends_at,
+                votes_for: U128::from(proposal.votes_for),
+                votes_against: U128::from(proposal.votes_against),
+            })
+    }
+
+    pub fn get_proposals(&self, from_index: u64, limit: u64) -> Vec<ProposalView> {
+        (from_index..self.proposals.len().min(from_index.saturating_add(limit)))
+            .filter_map(|proposal_id| self.get_proposal(proposal_id))
+            .collect()
+    }
+}
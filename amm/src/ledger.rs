@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedSet;
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId, Balance, Promise, StorageUsage};
+
+use crate::*;
+
+// Max length of NEAR account id
+const MAX_ACCOUNT_ID_LEN: StorageUsage = 64;
+
+// Storage of one record in trie: 40 bytes of record overhead, key (prefix + account id)
+// and value (storage balance + storage used by other records + empty deposits and locked maps)
+pub const ACCOUNT_STORAGE_USAGE: StorageUsage = 40 + (1 + 4 + MAX_ACCOUNT_ID_LEN) + 16 + 8 + 4 + 4;
+
+// Storage of the account in the depositors of the token: two records of `UnorderedSet`,
+// index by account id and account id by index. Prefix of the set is 9 bytes (see `depositors_prefix`)
+const DEPOSITOR_STORAGE_USAGE: StorageUsage =
+    (40 + (9 + 1 + 4 + MAX_ACCOUNT_ID_LEN) + 8) + (40 + (9 + 1 + 8) + (4 + MAX_ACCOUNT_ID_LEN));
+
+// Storage of one token entry in the deposits map (token id + balance) and in depositors of the token
+pub const DEPOSIT_STORAGE_USAGE: StorageUsage =
+    (4 + MAX_ACCOUNT_ID_LEN) + 16 + DEPOSITOR_STORAGE_USAGE;
+
+// Ledger record of a single account: deposited tokens and NEAR staked by the account
+// to pay for the storage of the record and of other records owned by the account
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct Account {
+    pub storage_balance: Balance,
+    // Bytes of the account records kept outside of the ledger (e.g. LP shares)
+    pub storage_used: StorageUsage,
+    pub deposits: HashMap<AccountId, Balance>,
+    // Tokens of pending transfers from the deposits, they are released by the callback
+    // of the transfer, so their storage is covered by AMM
+    pub locked: HashMap<AccountId, Balance>,
+    // Tokens deposited for the first time, the account is added to their depositors on save
+    #[borsh_skip]
+    new_tokens: Vec<AccountId>,
+}
+
+impl Account {
+    // Bytes occupied by the account record in the ledger and by other records of the account
+    pub fn storage_usage(&self) -> StorageUsage {
+        ACCOUNT_STORAGE_USAGE
+            + self.deposits.len() as StorageUsage * DEPOSIT_STORAGE_USAGE
+            + self.storage_used
+    }
+
+    pub fn is_storage_covered(&self) -> bool {
+        self.storage_balance >= Balance::from(self.storage_usage()) * env::storage_byte_cost()
+    }
+
+    // NEAR that is not locked by the storage of the account record
+    pub fn storage_available(&self) -> Balance {
+        let locked = Balance::from(self.storage_usage()) * env::storage_byte_cost();
+        self.storage_balance.saturating_sub(locked)
+    }
+
+    pub fn balance_of(&self, token_id: &AccountId) -> Balance {
+        self.deposits.get(token_id).copied().unwrap_or(0)
+    }
+
+    pub fn deposit(&mut self, token_id: &AccountId, amount: Balance) {
+        let balance = self.balance_of(token_id);
+        let new_balance = balance
+            .checked_add(amount)
+            .or_fail(AmmError::BalanceOverflow);
+        self.set_balance(token_id, new_balance);
+    }
+
+    pub fn withdraw(&mut self, token_id: &AccountId, amount: Balance) {
+        let balance = self.balance_of(token_id);
+        if balance < amount {
+            fail(AmmError::NotEnoughDeposit)
+        }
+        self.set_balance(token_id, balance - amount);
+    }
+
+    pub fn locked_of(&self, token_id: &AccountId) -> Balance {
+        self.locked.get(token_id).copied().unwrap_or(0)
+    }
+
+    // Moves tokens from the deposit to the locked balance
+    pub fn lock(&mut self, token_id: &AccountId, amount: Balance) {
+        self.withdraw(token_id, amount);
+        *self.locked.entry(token_id.clone()).or_default() += amount;
+    }
+
+    // Drops tokens from the locked balance, the caller decides where they go
+    pub fn unlock(&mut self, token_id: &AccountId, amount: Balance) {
+        let locked = self.locked_of(token_id);
+        if locked < amount {
+            fail(AmmError::NotEnoughLockedTokens)
+        }
+        if locked == amount {
+            self.locked.remove(token_id);
+        } else {
+            self.locked.insert(token_id.clone(), locked - amount);
+        }
+    }
+
+    fn set_balance(&mut self, token_id: &AccountId, balance: Balance) {
+        if self.deposits.insert(token_id.clone(), balance).is_none() {
+            self.new_tokens.push(token_id.clone());
+        }
+    }
+}
+
+// Depositors of every token are kept in their own set, its prefix is built
+// from the hash of the token id to have a fixed length
+fn depositors_prefix(token_id: &AccountId) -> Vec<u8> {
+    [
+        b"n".to_vec(),
+        env::sha256(token_id.as_bytes())[..8].to_vec(),
+    ]
+    .concat()
+}
+
+impl AMM {
+    pub(crate) fn internal_get_account(&self, account_id: &AccountId) -> Option<Account> {
+        self.accounts.get(account_id)
+    }
+
+    pub(crate) fn internal_unwrap_account(&self, account_id: &AccountId) -> Account {
+        self.internal_get_account(account_id)
+            .or_fail(AmmError::AccountNotRegistered)
+    }
+
+    // Saves the account record. The storage of the record must be covered by
+    // the storage balance of the account
+    pub(crate) fn internal_save_account(&mut self, account_id: &AccountId, account: &Account) {
+        if !account.is_storage_covered() {
+            fail(AmmError::NotEnoughStorage)
+        }
+        self.accounts.insert(account_id, account);
+        self.internal_add_depositor(account_id, account);
+    }
+
+    // Adds the account to depositors of tokens it deposited for the first time
+    fn internal_add_depositor(&mut self, account_id: &AccountId, account: &Account) {
+        for token_id in account.new_tokens.iter() {
+            let mut depositors = self
+                .depositors
+                .get(token_id)
+                .unwrap_or_else(|| UnorderedSet::new(depositors_prefix(token_id)));
+            depositors.insert(account_id);
+            self.depositors.insert(token_id, &depositors);
+        }
+    }
+
+    pub(crate) fn internal_balance_of(
+        &self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+    ) -> Balance {
+        self.internal_get_account(account_id)
+            .map(|account| account.balance_of(token_id))
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn internal_deposit(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: Balance,
+    ) {
+        let mut account = self.internal_unwrap_account(account_id);
+        account.deposit(token_id, amount);
+        self.internal_save_account(account_id, &account);
+    }
+
+    // Deposits tokens only if the account is registered and its storage covers the deposit
+    pub(crate) fn internal_try_deposit(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: Balance,
+    ) -> bool {
+        match self.internal_get_account(account_id) {
+            Some(mut account) => {
+                account.deposit(token_id, amount);
+                if !account.is_storage_covered() {
+                    return false;
+                }
+                self.accounts.insert(account_id, &account);
+                self.internal_add_depositor(account_id, &account);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn internal_withdraw(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: Balance,
+    ) {
+        let mut account = self.internal_unwrap_account(account_id);
+        account.withdraw(token_id, amount);
+        self.internal_save_account(account_id, &account);
+    }
+
+    // Storage staking of the ledger
+
+    pub(crate) fn ledger_storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128::from(Balance::from(ACCOUNT_STORAGE_USAGE) * env::storage_byte_cost()),
+            max: None,
+        }
+    }
+
+    pub(crate) fn ledger_storage_balance_of(
+        &self,
+        account_id: &AccountId,
+    ) -> Option<StorageBalance> {
+        self.internal_get_account(account_id)
+            .map(|account| StorageBalance {
+                total: U128::from(account.storage_balance),
+                available: U128::from(account.storage_available()),
+            })
+    }
+
+    // Registers the account if needed and adds the attached deposit to its storage balance.
+    // With `registration_only` everything above the minimal balance is refunded
+    pub(crate) fn ledger_storage_deposit(
+        &mut self,
+        account_id: &AccountId,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let min_balance = self.ledger_storage_balance_bounds().min.0;
+        let registration_only = registration_only.unwrap_or(false);
+        match self.internal_get_account(account_id) {
+            Some(mut account) => {
+                if registration_only {
+                    if amount > 0 {
+                        Promise::new(env::predecessor_account_id()).transfer(amount);
+                    }
+                } else {
+                    account.storage_balance += amount;
+                    self.internal_save_account(account_id, &account);
+                }
+            }
+            None => {
+                if amount < min_balance {
+                    fail(AmmError::StorageDepositTooSmall)
+                }
+                let deposit = if registration_only {
+                    let refund = amount - min_balance;
+                    if refund > 0 {
+                        Promise::new(env::predecessor_account_id()).transfer(refund);
+                    }
+                    min_balance
+                } else {
+                    amount
+                };
+                let account = Account {
+                    storage_balance: deposit,
+                    ..Default::default()
+                };
+                self.internal_save_account(account_id, &account);
+            }
+        }
+        self.ledger_storage_balance_of(account_id)
+            .or_fail(AmmError::AccountNotRegistered)
+    }
+
+    pub(crate) fn ledger_storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        let account_id = env::predecessor_account_id();
+        let mut account = self.internal_unwrap_account(&account_id);
+        let available = account.storage_available();
+        let amount = amount.map(|amount| amount.0).unwrap_or(available);
+        if amount > available {
+            fail(AmmError::StorageWithdrawTooLarge)
+        }
+        if amount > 0 {
+            account.storage_balance -= amount;
+            self.internal_save_account(&account_id, &account);
+            Promise::new(account_id.clone()).transfer(amount);
+        }
+        self.ledger_storage_balance_of(&account_id)
+            .or_fail(AmmError::AccountNotRegistered)
+    }
+
+    // Removes the account from the ledger and returns its storage balance.
+    // Deposits are dropped only with `force`
+    pub(crate) fn ledger_storage_unregister(&mut self, force: Option<bool>) -> bool {
+        let account_id = env::predecessor_account_id();
+        if let Some(account) = self.internal_get_account(&account_id) {
+            if !account.locked.is_empty() {
+                fail(AmmError::UnregisterWithPendingTransfers)
+            }
+            let has_deposits = account.deposits.values().any(|balance| *balance > 0);
+            if has_deposits && !force.unwrap_or(false) {
+                fail(AmmError::UnregisterWithDeposits)
+            }
+            self.accounts.remove(&account_id);
+            for (token_id, balance) in account.deposits.iter() {
+                if let Some(mut depositors) = self.depositors.get(token_id) {
+                    depositors.remove(&account_id);
+                    self.depositors.insert(token_id, &depositors);
+                }
+                self.internal_remove_held(token_id, *balance);
+            }
+            Promise::new(account_id).transfer(account.storage_balance);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // All deposited tokens of the account
+    pub fn get_deposits(&self, account_id: AccountId) -> HashMap<AccountId, U128> {
+        self.internal_get_account(&account_id)
+            .map(|account| {
+                account
+                    .deposits
+                    .into_iter()
+                    .map(|(token_id, balance)| (token_id, U128::from(balance)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn get_deposit(&self, account_id: AccountId, token_id: AccountId) -> U128 {
+        U128::from(self.internal_balance_of(&account_id, &token_id))
+    }
+
+    // Accounts that have deposited the token with their balances
+    pub fn get_depositors(
+        &self,
+        token_id: AccountId,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<(AccountId, U128)> {
+        let depositors = match self.depositors.get(&token_id) {
+            Some(depositors) => depositors,
+            None => return vec![],
+        };
+        let accounts = depositors.as_vector();
+        (from_index..accounts.len().min(from_index.saturating_add(limit)))
+            .filter_map(|index| accounts.get(index))
+            .map(|account_id| {
+                let balance = self.internal_balance_of(&account_id, &token_id);
+                (account_id, U128::from(balance))
+            })
+            .collect()
+    }
+}
@@ -1,346 +1,568 @@
 extern crate core;
 
-use std::cmp::max;
-
-use near_contract_standards::fungible_token::core::FungibleTokenCore;
 use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
-use near_contract_standards::fungible_token::FungibleToken;
-use near_contract_standards::storage_management::{
-    StorageBalance, StorageBalanceBounds, StorageManagement,
-};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LookupMap;
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::ext_contract;
 use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json;
 use near_sdk::{
-    env, log, near_bindgen, AccountId, Gas, PanicOnDefault, PromiseOrValue, PromiseResult,
+    assert_one_yocto, env, log, near_bindgen, AccountId, Balance, PanicOnDefault, Promise,
+    PromiseOrValue,
 };
 
-use crate::utils::{add_decimals, calc_dy, remove_decimals};
-
+use crate::errors::{fail, fail_with, OrFail};
+use crate::farming::{Farm, Stake};
+use crate::governance::{Proposal, TotalVotingPower, VotingPower};
+use crate::ledger::Account;
+use crate::limit_orders::LimitOrder;
+use crate::multisig::MultisigRequest;
+use crate::pool::{Pool, DEFAULT_FEE_BPS};
+use crate::timelock::QueuedAction;
+use crate::transfers::PendingTransfer;
+use crate::twamm::TwammOrder;
+use crate::wnear::near_token_id;
+
+pub use crate::actions::{Action, ActionAmount, ActionResult};
+pub use crate::admin::AdminAction;
+pub use crate::dynamic_fee::DynamicFeeConfig;
+pub use crate::errors::AmmError;
+pub use crate::ext::ext_amm;
+pub use crate::farming::{FarmView, StakeView, LOCK_BOOSTS};
+pub use crate::governance::{GovernanceConfig, ProposalStatus, ProposalView};
+pub use crate::limit_orders::{LimitOrderView, PRICE_PRECISION};
+pub use crate::multisig::{MultisigConfig, MultisigRequestView};
+pub use crate::pool::MIN_LIQUIDITY;
+pub use crate::positions::PositionView;
+pub use crate::stats::{PoolStatsView, VolumeBucket};
+pub use crate::timelock::{QueuedActionView, TimelockConfig};
+pub use crate::transfers::PendingTransferView;
+pub use crate::twamm::TwammOrderView;
+pub use crate::views::{PoolInfo, PoolView};
+
+mod actions;
+mod admin;
+mod dynamic_fee;
+mod errors;
+mod events;
+mod ext;
+mod farming;
+mod governance;
+mod ledger;
+mod limit_orders;
+mod multisig;
+mod owner;
+mod pool;
+mod positions;
+mod referral;
+mod skim;
+mod stats;
+mod storage;
+mod timelock;
+mod transfers;
+mod twamm;
 mod utils;
+mod views;
+mod wnear;
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct AMM {
-    // Tokens A and B (token, metadata, issuer)
-    pub tokens: LookupMap<AccountId, (FungibleToken, FungibleTokenMetadata)>,
+    // Account allowed to change the pool parameters
+    pub owner_id: AccountId,
 
-    // Here the proportions of the investment in the pool are stored
-    pub token_amm: FungibleToken,
-}
+    // Receiver of the protocol fees
+    pub treasury_id: AccountId,
+
+    // Metadata of supported tokens, any of them can be NEAR (see `NEAR_TOKEN_ID`)
+    pub tokens: LookupMap<AccountId, FungibleTokenMetadata>,
+
+    // Deposits of users by token
+    pub accounts: LookupMap<AccountId, Account>,
+
+    // Accounts that have deposited the token
+    pub depositors: LookupMap<AccountId, UnorderedSet<AccountId>>,
+
+    // Amounts of tokens held for users, the rest of the balance of AMM is the excess
+    pub held_tokens: LookupMap<AccountId, Balance>,
+
+    // Transfers from deposits waiting for their callbacks by id
+    pub pending_transfers: LookupMap<u64, PendingTransfer>,
+    pub next_transfer_id: u64,
+
+    // Pools by id, a pair of tokens can have a pool for every fee tier
+    pub pools: Vector<Pool>,
 
-fn init_token(account_id: &AccountId, prefix: Vec<u8>) -> FungibleToken {
-    let mut a = FungibleToken::new(prefix);
-    a.internal_register_account(account_id);
-    a
+    // Pool ids by sorted pair of tokens and fee tier
+    pub pool_ids: LookupMap<(AccountId, AccountId, u32), u64>,
+
+    // Registered referrers with their part of the swap fee in basis points of the fee
+    pub referrals: LookupMap<AccountId, u32>,
+
+    // Open limit orders by id and ids of open orders by maker
+    pub limit_orders: LookupMap<u64, LimitOrder>,
+    pub account_orders: LookupMap<AccountId, Vec<u64>>,
+    pub next_order_id: u64,
+
+    // Long-running orders by id and ids of long-running orders by maker
+    pub twamm_orders: LookupMap<u64, TwammOrder>,
+    pub account_twamm_orders: LookupMap<AccountId, Vec<u64>>,
+    pub next_twamm_order_id: u64,
+
+    // Reward farms by pool id and staked LP shares by pool id and account
+    pub farms: LookupMap<u64, Farm>,
+    pub stakes: LookupMap<(u64, AccountId), Stake>,
+
+    // Governance replacing the owner, proposals by id and votes by proposal and account
+    pub governance: Option<GovernanceConfig>,
+    pub proposals: Vector<Proposal>,
+    pub votes: LookupMap<(u64, AccountId), bool>,
+    pub voting_powers: LookupMap<AccountId, VotingPower>,
+    pub total_voting_powers: LookupMap<AccountId, TotalVotingPower>,
+
+    // Delay of owner actions with guardians cancelling them and queued actions by id
+    pub timelock: TimelockConfig,
+    pub queued_actions: UnorderedMap<u64, QueuedAction>,
+    pub next_action_id: u64,
+
+    // Members confirming owner actions and their requests by id
+    pub multisig: Option<MultisigConfig>,
+    pub multisig_requests: UnorderedMap<u64, MultisigRequest>,
+    pub next_multisig_request_id: u64,
 }
 
 // Define an interface for callbacks
 #[ext_contract(ext_self)]
 trait SelfContract {
-    fn withdraw_tokens_callback(&mut self, token_name: String, amount: U128);
+    fn on_claim_protocol_fee(&mut self, pool_id: u64, token_id: AccountId, amount: U128);
+    fn on_transfer(&mut self, transfer_id: u64) -> bool;
+    fn on_transfer_call(&mut self, transfer_id: u64) -> U128;
+    fn on_skim(&mut self, token_id: AccountId, max_amount: U128);
+    fn on_sync(&mut self, pool_id: u64, token_id: AccountId, max_amount: U128);
+}
+
+// Result of the swap with the fee actually applied
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SwapResult {
+    pub buy_amount: U128,
+    pub fee: U128,
+    pub fee_bps: u32,
+}
+
+// Message of `ft_transfer_call`, empty message just deposits tokens
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum TokenReceiverMessage {
+    // Adds rewards to the farm of the pool, the rate is set by the owner
+    FundFarm {
+        pool_id: u64,
+    },
+    // Swaps received tokens for `buy_token_id` on behalf of the sender and sends them
+    // with `ft_transfer_call` to the receiver contract with `msg`
+    SwapAndCall {
+        buy_token_id: AccountId,
+        min_amount_out: Option<U128>,
+        referral_id: Option<AccountId>,
+        fee_bps: Option<u32>,
+        receiver_id: AccountId,
+        msg: String,
+    },
 }
 
 #[ext_contract(ext_ft)]
 trait FtContract {
     fn ft_transfer(&self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+    fn ft_transfer_call(
+        &self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> U128;
+    fn ft_balance_of(&self, account_id: AccountId) -> U128;
+}
+
+// Key of the pool in `pool_ids`, it doesn't depend on the order of tokens
+fn pool_key(token_a: &AccountId, token_b: &AccountId, fee_bps: u32) -> (AccountId, AccountId, u32) {
+    if token_a < token_b {
+        (token_a.clone(), token_b.clone(), fee_bps)
+    } else {
+        (token_b.clone(), token_a.clone(), fee_bps)
+    }
 }
 
-#[near_bindgen]
 impl AMM {
-    #[init]
-    pub fn new(
-        token_a_contract: AccountId,
-        token_b_contract: AccountId,
-        token_a_metadata: FungibleTokenMetadata,
-        token_b_metadata: FungibleTokenMetadata,
-    ) -> Self {
-        assert!(!env::state_exists(), "Already initialized");
-        let owner_id = env::current_account_id();
-        let token_a = init_token(&owner_id, b"a".to_vec());
-        let token_b = init_token(&owner_id, b"b".to_vec());
-        let token_amm = init_token(&owner_id, b"amm".to_vec());
-        let mut tokens = LookupMap::new(b"m".to_vec());
-        tokens.insert(&token_a_contract, &(token_a, token_a_metadata));
-        tokens.insert(&token_b_contract, &(token_b, token_b_metadata));
-        Self { tokens, token_amm }
+    pub(crate) fn internal_get_pool_id(
+        &self,
+        token_a: &AccountId,
+        token_b: &AccountId,
+        fee_bps: Option<u32>,
+    ) -> Option<u64> {
+        let fee_bps = fee_bps.unwrap_or(DEFAULT_FEE_BPS);
+        self.pool_ids.get(&pool_key(token_a, token_b, fee_bps))
     }
 
-    pub fn swap(
+    // Finds the pool of tokens with the fee tier (default one if not specified)
+    pub(crate) fn internal_find_pool(
+        &self,
+        token_a: &AccountId,
+        token_b: &AccountId,
+        fee_bps: Option<u32>,
+    ) -> u64 {
+        if token_a.eq(token_b) {
+            fail(AmmError::TokensAreEqual)
+        }
+        if !self.tokens.contains_key(token_a) || !self.tokens.contains_key(token_b) {
+            fail(AmmError::TokenNotSupported);
+        }
+        self.internal_get_pool_id(token_a, token_b, fee_bps)
+            .or_fail(AmmError::PoolNotFound)
+    }
+
+    pub(crate) fn internal_unwrap_pool(&self, pool_id: u64) -> Pool {
+        self.pools.get(pool_id).or_fail(AmmError::PoolNotFound)
+    }
+
+    pub(crate) fn internal_save_pool(&mut self, pool_id: u64, pool: &Pool) {
+        self.pools.replace(pool_id, pool);
+    }
+
+    // Creates an empty pool of listed tokens with the fee tier
+    pub(crate) fn internal_add_pool(
         &mut self,
-        buy_token_name: AccountId,
-        sell_token_name: AccountId,
+        token_a: AccountId,
+        token_b: AccountId,
+        fee_bps: u32,
+    ) -> u64 {
+        if token_a.eq(&token_b) {
+            fail(AmmError::TokensAreEqual)
+        }
+        if !pool::FEE_TIERS.contains(&fee_bps) {
+            fail(AmmError::FeeTierNotSupported)
+        }
+        let key = pool_key(&token_a, &token_b, fee_bps);
+        if self.pool_ids.contains_key(&key) {
+            fail(AmmError::PoolAlreadyExists)
+        }
+        let decimals = [&token_a, &token_b]
+            .iter()
+            .map(|token_id| {
+                self.tokens
+                    .get(token_id)
+                    .or_fail(AmmError::TokenNotSupported)
+                    .decimals
+            })
+            .collect();
+        let pool_id = self.pools.len();
+        self.pools.push(&Pool::new(
+            pool_id,
+            vec![token_a, token_b],
+            decimals,
+            fee_bps,
+        ));
+        self.pool_ids.insert(&key, &pool_id);
+        log!("Pool {} has been added", pool_id);
+        pool_id
+    }
+
+    // Swap of tokens through the pool with the fee tier (default one if not specified).
+    // Registered referrer gets a part of the swap fee in sell tokens.
+    // Pools with the dynamic fee charge more after volatile periods
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn internal_swap(
+        &mut self,
+        user_account_id: &AccountId,
+        buy_token_name: &AccountId,
+        sell_token_name: &AccountId,
         sell_amount: U128,
-    ) -> U128 {
-        if buy_token_name.eq(&sell_token_name) {
-            panic!("Tokens can't be equals")
+        min_amount_out: Option<U128>,
+        referral_id: Option<&AccountId>,
+        fee_bps: Option<u32>,
+    ) -> SwapResult {
+        let pool_id = self.internal_find_pool(buy_token_name, sell_token_name, fee_bps);
+        let mut pool = self.internal_load_pool(pool_id);
+        let now = env::block_timestamp();
+
+        // Send sell_tokens to pool from seller, the protocol and referral parts
+        // of the fee are accrued separately from the pool reserves
+        let applied_fee_bps = pool.current_fee_bps(now);
+        let (fee, protocol_fee) = pool.calc_fees(sell_amount.0, applied_fee_bps);
+        let mut referral_fee = self.internal_calc_referral_fee(referral_id, fee, protocol_fee);
+        self.internal_withdraw(user_account_id, sell_token_name, sell_amount.0);
+        if referral_fee > 0
+            && !self.internal_try_deposit(referral_id.unwrap(), sell_token_name, referral_fee)
+        {
+            log!("Referral fee is left in the pool, the referrer can't receive it");
+            referral_fee = 0;
         }
 
-        // Get tokens by names
-        let mut buy_token = self
-            .tokens
-            .get(&buy_token_name)
-            .expect("Token not supported");
-        let mut sell_token = self
-            .tokens
-            .get(&sell_token_name)
-            .expect("Token not supported");
-        let pool_owner_id = env::current_account_id();
-        let user_account_id = env::predecessor_account_id();
-
-        // Get current statement of pool
-        let x = sell_token.0.internal_unwrap_balance_of(&pool_owner_id);
-        let y = buy_token.0.internal_unwrap_balance_of(&pool_owner_id);
-
-        // Send sell_tokens to pool from seller
-        sell_token
-            .0
-            .internal_transfer(&user_account_id, &pool_owner_id, sell_amount.0, None);
-
-        // Convert to the same decimal
-        let max_decimals = max(buy_token.1.decimals, sell_token.1.decimals);
-        let x = add_decimals(x, max_decimals - sell_token.1.decimals);
-        let y = add_decimals(y, max_decimals - buy_token.1.decimals);
-
-        // Calc buy amount
-        let buy_amount = calc_dy(x, y, sell_amount.0);
-
-        // Restore decimal
-        let buy_amount = remove_decimals(buy_amount, max_decimals - buy_token.1.decimals);
+        // Calc buy amount by the current statement of pool
+        let sell_token_index = pool.token_index(sell_token_name);
+        let buy_amount = pool.calc_buy_amount(sell_token_index, sell_amount.0 - fee);
+        if let Some(max_price_impact_bps) = pool.max_price_impact_bps {
+            let price_impact_bps =
+                pool.price_impact_bps(sell_token_index, sell_amount.0 - fee, buy_amount);
+            if price_impact_bps > u128::from(max_price_impact_bps) {
+                fail_with(
+                    AmmError::PriceImpactTooHigh,
+                    format!(
+                        "{} bps, maximum {} bps",
+                        price_impact_bps, max_price_impact_bps
+                    ),
+                )
+            }
+        }
+        if let Some(min_amount_out) = min_amount_out {
+            if buy_amount < min_amount_out.0 {
+                fail_with(
+                    AmmError::BuyAmountTooSmall,
+                    format!("{}, minimum {}", buy_amount, min_amount_out.0),
+                )
+            }
+        }
+
+        // Update the pool reserves
+        let reserves_before = pool.reserves.clone();
+        pool.apply_swap(
+            sell_token_index,
+            sell_amount.0 - referral_fee,
+            buy_amount,
+            protocol_fee,
+        );
+
+        pool.stats
+            .record_swap(sell_token_index, sell_amount.0, fee, now);
 
         // Send buy value to user buyer
-        buy_token
-            .0
-            .internal_transfer(&pool_owner_id, &user_account_id, buy_amount, None);
+        self.internal_deposit(user_account_id, buy_token_name, buy_amount);
+
+        events::Swap {
+            account_id: user_account_id,
+            pool_id,
+            sell_token_id: sell_token_name,
+            buy_token_id: buy_token_name,
+            sell_amount,
+            buy_amount: U128::from(buy_amount),
+            fee: U128::from(fee),
+            fee_bps: applied_fee_bps,
+            protocol_fee: U128::from(protocol_fee),
+            referral_id: referral_id.filter(|_| referral_fee > 0),
+            referral_fee: U128::from(referral_fee),
+        }
+        .emit();
 
-        // Update tokens data in lookup map
-        self.tokens.insert(&buy_token_name, &buy_token);
-        self.tokens.insert(&sell_token_name, &sell_token);
+        // Fill limit orders crossed by the new price and update the volatility
+        self.internal_fill_limit_orders(pool_id, &mut pool, now);
+        pool.observe_price(&reserves_before, now);
+        self.internal_save_pool(pool_id, &pool);
 
-        // Return both amount
-        U128::from(buy_amount)
+        SwapResult {
+            buy_amount: U128::from(buy_amount),
+            fee: U128::from(fee),
+            fee_bps: applied_fee_bps,
+        }
     }
 
-    // Adding tokens to the liquidity pool.
+    // Adding tokens to the liquidity pool with the fee tier (default one if not specified).
     // Tokens can only be added in proportion to the amount in the pool
-    pub fn add_tokens_to_pool(
+    pub(crate) fn internal_add_liquidity(
         &mut self,
-        token_a_name: AccountId,
+        payer_id: &AccountId,
+        token_a_name: &AccountId,
         token_a_amount: U128,
-        token_b_name: AccountId,
+        token_b_name: &AccountId,
         token_b_amount: U128,
-    ) {
-        if token_a_name.eq(&token_b_name) {
-            panic!("Tokens can't be equals")
+        fee_bps: Option<u32>,
+    ) -> Balance {
+        let pool_id = self.internal_find_pool(token_a_name, token_b_name, fee_bps);
+        let mut pool = self.internal_load_pool(pool_id);
+
+        // Amounts in the order of pool tokens
+        let mut amounts = vec![token_a_amount.0, token_b_amount.0];
+        if pool.token_index(token_a_name) != 0 {
+            amounts.reverse();
         }
 
-        // Get tokens by names
-        let mut token_a = self.tokens.get(&token_a_name).expect("Token not supported");
-        let mut token_b = self.tokens.get(&token_b_name).expect("Token not supported");
-        let pool_owner_id = env::current_account_id();
-        let payer_id = env::predecessor_account_id();
-
-        // Get current state of pool
-        let pool_a_balance = token_a.0.internal_unwrap_balance_of(&pool_owner_id);
-        let pool_b_balance = token_b.0.internal_unwrap_balance_of(&pool_owner_id);
-
-        // Сonvert to the same decimal
-        let max_decimals = max(token_a.1.decimals, token_b.1.decimals);
-
-        // We can add tokens to the pool only by proportionally increasing them
-        if pool_a_balance * token_b_amount.0 == pool_b_balance * token_a_amount.0 {
-            token_a
-                .0
-                .internal_transfer(&payer_id, &pool_owner_id, token_a_amount.0, None);
-            token_b
-                .0
-                .internal_transfer(&payer_id, &pool_owner_id, token_b_amount.0, None);
-            // Calc share of added tokens
-            let share = add_decimals(token_a_amount.0, max_decimals - token_a.1.decimals)
-                + add_decimals(token_b_amount.0, max_decimals - token_a.1.decimals);
-
-            // Store share
-            self.token_amm.internal_deposit(&payer_id, share);
-            log!("Share {} has been added to account {}", share, &payer_id);
-
-            // Update tokens data in lookup map
-            self.tokens.insert(&token_a_name, &token_a);
-            self.tokens.insert(&token_b_name, &token_b);
-        } else {
-            panic!("incorrect proportions for replenishing the liquidity pool")
-        }
+        self.internal_withdraw(payer_id, token_a_name, token_a_amount.0);
+        self.internal_withdraw(payer_id, token_b_name, token_b_amount.0);
+        self.internal_register_shares(&mut pool, payer_id);
+        let share = pool.add_liquidity(payer_id, &amounts);
+        self.internal_save_pool(pool_id, &pool);
+        log!("Share {} has been added to account {}", share, payer_id);
+        share
     }
 
-    // Here we are excluding all tokens of signed account from
-    // liquidity pool and return those tokens back to predecessor_account_id
-    // in the right proportion
-    pub fn exclude_tokens_from_pool(&mut self, token_a_name: AccountId, token_b_name: AccountId) {
-        if token_a_name.eq(&token_b_name) {
-            panic!("Tokens can't be equals")
+    // Excludes shares of the account (all if not specified) from the pool and returns
+    // tokens for them to the account deposits in the right proportion
+    pub(crate) fn internal_remove_liquidity(
+        &mut self,
+        account_id: &AccountId,
+        token_a_name: &AccountId,
+        token_b_name: &AccountId,
+        shares: Option<Balance>,
+        fee_bps: Option<u32>,
+    ) -> Vec<(AccountId, Balance)> {
+        let pool_id = self.internal_find_pool(token_a_name, token_b_name, fee_bps);
+        let mut pool = self.internal_load_pool(pool_id);
+
+        // Calc owned user tokens in pool in proportion and clear user share value
+        let shares = shares.unwrap_or_else(|| pool.shares_of(account_id));
+        let amounts = pool.remove_liquidity(account_id, shares);
+        self.internal_unregister_shares(&mut pool, account_id);
+        self.internal_save_pool(pool_id, &pool);
+
+        // Transfer tokens from pool to user deposits
+        let mut received = vec![];
+        for (token_id, amount) in pool.token_ids.into_iter().zip(amounts) {
+            self.internal_deposit(account_id, &token_id, amount);
+            received.push((token_id, amount));
         }
-        let mut token_a = self.tokens.get(&token_a_name).expect("Token not supported");
-        let mut token_b = self.tokens.get(&token_b_name).expect("Token not supported");
-
-        let pool_owner_id = env::current_account_id();
-        let pool_total_a = token_a.0.internal_unwrap_balance_of(&pool_owner_id);
-        let pool_total_b = token_b.0.internal_unwrap_balance_of(&pool_owner_id);
-        let predecessor_account_id = env::predecessor_account_id();
-
-        // Calc all owned user tokens in pool in proportion
-        let a = self.token_amm.total_supply * pool_total_a
-            / self
-                .token_amm
-                .internal_unwrap_balance_of(&predecessor_account_id);
-        let b = self.token_amm.total_supply * pool_total_b
-            / self
-                .token_amm
-                .internal_unwrap_balance_of(&predecessor_account_id);
-
-        // Clear user share value
-        self.token_amm.internal_withdraw(
-            &predecessor_account_id,
-            self.token_amm
-                .internal_unwrap_balance_of(&predecessor_account_id),
-        );
-        // Transfer tokens from pool to user wallet
-        token_a
-            .0
-            .internal_transfer(&env::current_account_id(), &predecessor_account_id, a, None);
-        token_b
-            .0
-            .internal_transfer(&env::current_account_id(), &predecessor_account_id, b, None);
-        // Update tokens data in lookup map
-        self.tokens.insert(&token_a_name, &token_a);
-        self.tokens.insert(&token_b_name, &token_b);
+        received
     }
+}
 
-    #[payable]
-    pub fn withdraw_tokens(&mut self, token_name: AccountId, amount: U128) {
-        let account_id = env::predecessor_account_id();
-        if !self.tokens.contains_key(&token_name) {
-            panic!("Token not supported");
+#[near_bindgen]
+impl AMM {
+    // Initializes the contract with the pool of tokens A and B with the default fee tier
+    #[init]
+    pub fn new(
+        token_a_contract: AccountId,
+        token_b_contract: AccountId,
+        token_a_metadata: FungibleTokenMetadata,
+        token_b_metadata: FungibleTokenMetadata,
+    ) -> Self {
+        if env::state_exists() {
+            fail(AmmError::AlreadyInitialized)
         }
-        ext_ft::ft_transfer(
-            account_id,
-            amount,
-            None,
-            token_name.clone(),
-            1,
-            Gas::from(5_000_000_000_000),
-        )
-        .then(ext_self::withdraw_tokens_callback(
-            token_name.to_string(),
-            amount,
-            env::current_account_id(),
-            0,
-            Gas::from(5_000_000_000_000),
-        ));
+        let mut tokens = LookupMap::new(b"m".to_vec());
+        tokens.insert(&token_a_contract, &token_a_metadata);
+        tokens.insert(&token_b_contract, &token_b_metadata);
+        let mut this = Self {
+            owner_id: env::predecessor_account_id(),
+            treasury_id: env::predecessor_account_id(),
+            tokens,
+            accounts: LookupMap::new(b"d".to_vec()),
+            depositors: LookupMap::new(b"o".to_vec()),
+            held_tokens: LookupMap::new(b"h".to_vec()),
+            pending_transfers: LookupMap::new(b"j".to_vec()),
+            next_transfer_id: 0,
+            pools: Vector::new(b"p".to_vec()),
+            pool_ids: LookupMap::new(b"i".to_vec()),
+            referrals: LookupMap::new(b"r".to_vec()),
+            limit_orders: LookupMap::new(b"l".to_vec()),
+            account_orders: LookupMap::new(b"a".to_vec()),
+            next_order_id: 0,
+            twamm_orders: LookupMap::new(b"t".to_vec()),
+            account_twamm_orders: LookupMap::new(b"u".to_vec()),
+            next_twamm_order_id: 0,
+            farms: LookupMap::new(b"f".to_vec()),
+            stakes: LookupMap::new(b"k".to_vec()),
+            governance: None,
+            proposals: Vector::new(b"g".to_vec()),
+            votes: LookupMap::new(b"v".to_vec()),
+            voting_powers: LookupMap::new(b"q".to_vec()),
+            total_voting_powers: LookupMap::new(b"c".to_vec()),
+            timelock: TimelockConfig::default(),
+            queued_actions: UnorderedMap::new(b"x".to_vec()),
+            next_action_id: 0,
+            multisig: None,
+            multisig_requests: UnorderedMap::new(b"z".to_vec()),
+            next_multisig_request_id: 0,
+        };
+        this.internal_add_pool(token_a_contract, token_b_contract, DEFAULT_FEE_BPS);
+        this
     }
 
-    pub fn withdraw_tokens_callback(&mut self, token_name: AccountId, amount: U128) {
-        match env::promise_result(0) {
-            PromiseResult::NotReady => unreachable!(),
-            PromiseResult::Failed => "error!".to_string(),
-            PromiseResult::Successful(_) => {
-                // Get the user who sent the tokens
-                let account_id = env::signer_account_id();
-                let mut token = self.tokens.get(&token_name).unwrap();
-
-                // Clear sent tokens value
-                token.0.internal_withdraw(&account_id, amount.0);
-
-                // Update token in lookup map
-                self.tokens.insert(&token_name, &token);
-                "Ok".to_string()
-            }
-        };
+    // Called by the `upgrade` action after the deployment of the new code.
+    // Code changing the layout of the state converts the old state here
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        env::state_read().or_fail(AmmError::NotInitialized)
     }
 
-    pub fn ft_balance_of(&self, token_name: AccountId, account_id: AccountId) -> U128 {
-        if token_name == env::current_account_id() {
-            self.token_amm.ft_balance_of(account_id)
-        } else {
-            self.tokens
-                .get(&token_name)
-                .expect("Token not supported")
-                .0
-                .ft_balance_of(account_id)
-        }
+    // Swap of tokens through the pool with the fee tier (default one if not specified).
+    // Registered referrer gets a part of the swap fee in sell tokens.
+    // Pools with the dynamic fee charge more after volatile periods
+    pub fn swap(
+        &mut self,
+        buy_token_name: AccountId,
+        sell_token_name: AccountId,
+        sell_amount: U128,
+        min_amount_out: Option<U128>,
+        referral_id: Option<AccountId>,
+        fee_bps: Option<u32>,
+    ) -> SwapResult {
+        self.internal_swap(
+            &env::predecessor_account_id(),
+            &buy_token_name,
+            &sell_token_name,
+            sell_amount,
+            min_amount_out,
+            referral_id.as_ref(),
+            fee_bps,
+        )
     }
 
-    // Storage implementation (without testing)
-    #[payable]
-    #[allow(dead_code)]
-    pub fn storage_deposit(
+    // Adding tokens to the liquidity pool with the fee tier (default one if not specified).
+    // Tokens can only be added in proportion to the amount in the pool
+    pub fn add_tokens_to_pool(
         &mut self,
-        token_name: AccountId,
-        account_id: AccountId,
-        registration_only: Option<bool>,
+        token_a_name: AccountId,
+        token_a_amount: U128,
+        token_b_name: AccountId,
+        token_b_amount: U128,
+        fee_bps: Option<u32>,
     ) {
-        if token_name == env::current_account_id() {
-            self.token_amm
-                .storage_deposit(Some(account_id), registration_only);
-        } else {
-            let mut token = self.tokens.get(&token_name).unwrap();
-            token.0.storage_deposit(Some(account_id), registration_only);
-            self.tokens.insert(&token_name, &token);
-        }
+        self.internal_add_liquidity(
+            &env::predecessor_account_id(),
+            &token_a_name,
+            token_a_amount,
+            &token_b_name,
+            token_b_amount,
+            fee_bps,
+        );
     }
 
-    #[payable]
-    #[allow(dead_code)]
-    fn storage_withdraw(&mut self, token_name: AccountId, amount: Option<U128>) -> StorageBalance {
-        if token_name == env::current_account_id() {
-            self.token_amm.storage_withdraw(amount)
-        } else {
-            let mut token = self.tokens.get(&token_name).unwrap();
-            let storage_balance = token.0.storage_withdraw(amount);
-            self.tokens.insert(&token_name, &token);
-            storage_balance
-        }
+    // Here we are excluding all tokens of signed account from liquidity pool
+    // with the fee tier (default one if not specified) and return those tokens
+    // back to predecessor_account_id in the right proportion
+    pub fn exclude_tokens_from_pool(
+        &mut self,
+        token_a_name: AccountId,
+        token_b_name: AccountId,
+        fee_bps: Option<u32>,
+    ) {
+        self.internal_remove_liquidity(
+            &env::predecessor_account_id(),
+            &token_a_name,
+            &token_b_name,
+            None,
+            fee_bps,
+        );
     }
 
+    // Sends tokens from the deposit to the wallet, they are locked until the transfer is done
     #[payable]
-    #[allow(dead_code)]
-    fn storage_unregister(&mut self, token_name: AccountId, force: Option<bool>) -> bool {
-        if token_name == env::current_account_id() {
-            if let Some((_, _)) = self.token_amm.internal_storage_unregister(force) {
-                return true;
-            }
-        } else {
-            let mut token = self.tokens.get(&token_name).unwrap();
-            if let Some((_, _)) = token.0.internal_storage_unregister(force) {
-                self.tokens.insert(&token_name, &token);
-                return true;
-            }
+    pub fn withdraw_tokens(&mut self, token_name: AccountId, amount: U128) -> Promise {
+        assert_one_yocto();
+        if !self.tokens.contains_key(&token_name) {
+            fail(AmmError::TokenNotSupported);
         }
-        false
-    }
-
-    #[allow(dead_code)]
-    fn storage_balance_bounds(&self, token_name: AccountId) -> StorageBalanceBounds {
-        if token_name == env::current_account_id() {
-            self.token_amm.storage_balance_bounds()
-        } else {
-            let token = self.tokens.get(&token_name).unwrap();
-            token.0.storage_balance_bounds()
+        if token_name == near_token_id() {
+            fail(AmmError::NearWithdrawOnly);
         }
+        self.internal_start_transfer(&env::predecessor_account_id(), &token_name, amount.0)
     }
 
-    #[allow(dead_code)]
-    fn storage_balance_of(
-        &self,
-        token_name: AccountId,
-        account_id: AccountId,
-    ) -> Option<StorageBalance> {
-        if token_name == env::current_account_id() {
-            self.token_amm.storage_balance_of(account_id)
-        } else {
-            let token = self.tokens.get(&token_name).unwrap();
-            token.0.storage_balance_of(account_id)
+    pub fn ft_balance_of(&self, token_name: AccountId, account_id: AccountId) -> U128 {
+        if !self.tokens.contains_key(&token_name) {
+            fail(AmmError::TokenNotSupported);
         }
+        U128::from(self.internal_balance_of(&account_id, &token_name))
     }
 }
 
@@ -350,12 +572,53 @@ impl FungibleTokenReceiver for AMM {
         &mut self,
         sender_id: AccountId,
         amount: U128,
-        #[allow(unused_variables)] msg: String,
+        msg: String,
     ) -> PromiseOrValue<U128> {
         let token_name = &env::predecessor_account_id();
-        let mut token = self.tokens.get(token_name).expect("Token not supported");
-        token.0.internal_deposit(&sender_id, amount.0);
-        self.tokens.insert(token_name, &token);
+        if !self.tokens.contains_key(token_name) || token_name == &near_token_id() {
+            fail(AmmError::TokenNotSupported);
+        }
+        self.internal_add_held(token_name, amount.0);
+        if msg.is_empty() {
+            self.internal_deposit(&sender_id, token_name, amount.0);
+        } else {
+            let message: TokenReceiverMessage = serde_json::from_str(&msg)
+                .ok()
+                .or_fail(AmmError::InvalidMessage);
+            match message {
+                TokenReceiverMessage::FundFarm { pool_id } => {
+                    self.internal_fund_farm(token_name, amount.0, pool_id)
+                }
+                TokenReceiverMessage::SwapAndCall {
+                    buy_token_id,
+                    min_amount_out,
+                    referral_id,
+                    fee_bps,
+                    receiver_id,
+                    msg,
+                } => {
+                    // Sold tokens pass through the deposit of the sender, the result
+                    // is delivered by a detached promise, so all of them are used here
+                    self.internal_deposit(&sender_id, token_name, amount.0);
+                    let result = self.internal_swap(
+                        &sender_id,
+                        &buy_token_id,
+                        token_name,
+                        amount,
+                        min_amount_out,
+                        referral_id.as_ref(),
+                        fee_bps,
+                    );
+                    self.internal_start_transfer_call(
+                        &sender_id,
+                        &buy_token_id,
+                        result.buy_amount.0,
+                        receiver_id,
+                        msg,
+                    );
+                }
+            }
+        }
         PromiseOrValue::Value(U128::from(0_u128))
     }
 }
@@ -363,7 +626,9 @@ impl FungibleTokenReceiver for AMM {
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod tests {
-    use crate::{add_decimals, calc_dy, remove_decimals};
+    use crate::dynamic_fee::{DynamicFeeConfig, Volatility};
+    use crate::errors::AmmError;
+    use crate::utils::{add_decimals, calc_dy, remove_decimals};
 
     #[test]
     fn test_add_decimals() {
@@ -387,4 +652,45 @@ mod tests {
         let dy = remove_decimals(dy, max_decimals - 1);
         assert_eq!(dy, 20_000);
     }
+
+    #[test]
+    fn check_calculator_with_near_decimals() {
+        let x = 10_000; // 3 numbers float
+        let y = 10_u128.pow(25); // 10 NEAR, 24 numbers float
+        let max_decimals = 24;
+        let x = add_decimals(x, max_decimals - 3);
+        let dx = add_decimals(10_000, max_decimals - 3);
+        let dy = calc_dy(x, y, dx);
+        assert_eq!(dy, 5 * 10_u128.pow(24));
+    }
+
+    #[test]
+    fn check_dynamic_fee() {
+        let config = DynamicFeeConfig {
+            min_fee_bps: 5,
+            max_fee_bps: 105,
+            max_volatility_bps: 1_000,
+            decay_period_sec: 60,
+        };
+        assert_eq!(config.fee_bps(0), 5);
+        assert_eq!(config.fee_bps(500), 55);
+        assert_eq!(config.fee_bps(5_000), 105);
+
+        // Volatility falls by half after the decay period
+        let mut volatility = Volatility::default();
+        volatility.observe(&config, 0, 800);
+        assert_eq!(volatility.value(&config, 0), 800);
+        assert_eq!(volatility.value(&config, 60_000_000_000), 400);
+        volatility.observe(&config, 60_000_000_000, 100);
+        assert_eq!(volatility.value(&config, 60_000_000_000), 500);
+    }
+
+    #[test]
+    fn check_error_codes() {
+        assert_eq!(AmmError::AlreadyInitialized.code(), "E001");
+        assert_eq!(
+            AmmError::NotEnoughDeposit.to_string(),
+            "E021: Not enough tokens in deposit"
+        );
+    }
 }
@@ -0,0 +1,289 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Balance, StorageUsage, Timestamp};
+
+use crate::pool::FEE_DIVISOR;
+use crate::utils::{mul_div, U256};
+use crate::*;
+
+// Limit price is the amount of buy tokens for one unit of sell token multiplied by this value
+pub const PRICE_PRECISION: u128 = 1_000_000_000_000_000_000_000_000;
+
+// Max number of orders filled by one swap, so swaps don't run out of gas
+const MAX_FILLS_PER_SWAP: usize = 10;
+
+// Resting order to sell tokens to the pool when the pool price reaches the limit price.
+// Sell tokens are locked in the order, bought tokens go to the maker deposits
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LimitOrder {
+    pub account_id: AccountId,
+    pub pool_id: u64,
+    pub sell_token_id: AccountId,
+    pub buy_token_id: AccountId,
+    // Sell tokens left in the order
+    pub amount: Balance,
+    // Buy tokens received by fills so far
+    pub bought: Balance,
+    pub limit_price: u128,
+    // Bytes of the order records charged from the maker storage balance
+    pub storage_usage: StorageUsage,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LimitOrderView {
+    pub order_id: u64,
+    pub account_id: AccountId,
+    pub pool_id: u64,
+    pub sell_token_id: AccountId,
+    pub buy_token_id: AccountId,
+    pub amount: U128,
+    pub bought: U128,
+    pub limit_price: U128,
+}
+
+impl LimitOrderView {
+    fn new(order_id: u64, order: LimitOrder) -> Self {
+        Self {
+            order_id,
+            account_id: order.account_id,
+            pool_id: order.pool_id,
+            sell_token_id: order.sell_token_id,
+            buy_token_id: order.buy_token_id,
+            amount: U128::from(order.amount),
+            bought: U128::from(order.bought),
+            limit_price: U128::from(order.limit_price),
+        }
+    }
+}
+
+// Max amount of sell tokens the order can sell to the pool until the marginal price
+// falls to the limit price. The limit is raised by the fee, so the maker gets
+// at least the limit price after paying the fee
+fn calc_fill_amount(pool: &Pool, sell_index: usize, limit_price: u128, fee_bps: u32) -> Balance {
+    let x = U256::from(pool.reserves[sell_index]);
+    let y = U256::from(pool.reserves[1 - sell_index]);
+    if x.is_zero() || y.is_zero() || fee_bps >= FEE_DIVISOR {
+        return 0;
+    }
+    let price =
+        U256::from(limit_price) * U256::from(FEE_DIVISOR) / U256::from(FEE_DIVISOR - fee_bps);
+
+    // Marginal price after the fill is y' / x' and x' * y' = x * y,
+    // so the sell reserve reaches the limit at sqrt(x * y * PRICE_PRECISION / price)
+    let target = match (y * U256::from(PRICE_PRECISION) / price).checked_mul(x) {
+        Some(value) => value.integer_sqrt(),
+        None => return 0,
+    };
+    if target <= x {
+        0
+    } else {
+        (target - x).as_u128()
+    }
+}
+
+impl AMM {
+    fn internal_unwrap_limit_order(&self, order_id: u64) -> LimitOrder {
+        self.limit_orders
+            .get(&order_id)
+            .or_fail(AmmError::LimitOrderNotFound)
+    }
+
+    // Closes the order, returns unsold tokens to the maker and releases the order storage
+    fn internal_remove_limit_order(&mut self, pool: &mut Pool, order_id: u64, order: &LimitOrder) {
+        let sell_index = pool.token_index(&order.sell_token_id);
+        pool.order_books[sell_index].remove(&(order.limit_price, order_id));
+        self.limit_orders.remove(&order_id);
+        let mut order_ids = self
+            .account_orders
+            .get(&order.account_id)
+            .unwrap_or_default();
+        order_ids.retain(|id| *id != order_id);
+        if order_ids.is_empty() {
+            self.account_orders.remove(&order.account_id);
+        } else {
+            self.account_orders.insert(&order.account_id, &order_ids);
+        }
+
+        let mut account = self.internal_unwrap_account(&order.account_id);
+        account.deposit(&order.sell_token_id, order.amount);
+        account.storage_used -= order.storage_usage;
+        self.internal_save_account(&order.account_id, &account);
+    }
+
+    // Sells tokens of orders to the pool while the pool price is better than their limit.
+    // Orders with the lowest limit price are filled first, the last one can be filled partially.
+    // Orders too small to buy anything at their limit are closed, so they don't block the book
+    pub(crate) fn internal_fill_limit_orders(
+        &mut self,
+        pool_id: u64,
+        pool: &mut Pool,
+        now: Timestamp,
+    ) {
+        let mut fills = 0;
+        for sell_index in 0..pool.token_ids.len() {
+            while fills < MAX_FILLS_PER_SWAP {
+                let (limit_price, order_id) = match pool.order_books[sell_index].min() {
+                    Some(key) => key,
+                    None => break,
+                };
+                let fee_bps = pool.current_fee_bps(now);
+                let fill_amount = calc_fill_amount(pool, sell_index, limit_price, fee_bps);
+                let mut order = self.internal_unwrap_limit_order(order_id);
+                let sell_amount = fill_amount.min(order.amount);
+                let (fee, protocol_fee) = pool.calc_fees(sell_amount, fee_bps);
+                let buy_amount = pool.calc_buy_amount(sell_index, sell_amount - fee);
+                if fill_amount == 0 {
+                    break;
+                }
+                fills += 1;
+                if buy_amount == 0
+                    || buy_amount < mul_div(sell_amount, limit_price, PRICE_PRECISION)
+                {
+                    // The whole rest of the order fits the fill, but is too small for rounding
+                    if sell_amount == order.amount {
+                        self.internal_remove_limit_order(pool, order_id, &order);
+                        continue;
+                    }
+                    break;
+                }
+
+                pool.apply_swap(sell_index, sell_amount, buy_amount, protocol_fee);
+                pool.stats.record_swap(sell_index, sell_amount, fee, now);
+                order.amount -= sell_amount;
+                order.bought += buy_amount;
+                self.internal_deposit(&order.account_id, &order.buy_token_id, buy_amount);
+                events::LimitOrderFill {
+                    order_id,
+                    account_id: &order.account_id,
+                    pool_id,
+                    sell_token_id: &order.sell_token_id,
+                    buy_token_id: &order.buy_token_id,
+                    sell_amount: U128::from(sell_amount),
+                    buy_amount: U128::from(buy_amount),
+                    fee: U128::from(fee),
+                }
+                .emit();
+
+                if order.amount > 0 {
+                    self.limit_orders.insert(&order_id, &order);
+                    break;
+                }
+                self.internal_remove_limit_order(pool, order_id, &order);
+            }
+        }
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Locks `amount` of sell tokens from the deposit until the pool with the fee tier
+    // (default one if not specified) gives at least `limit_price` (see `PRICE_PRECISION`).
+    // The order is filled by swaps, bought tokens are credited to the deposit of the maker
+    pub fn place_limit_order(
+        &mut self,
+        sell_token: AccountId,
+        buy_token: AccountId,
+        amount: U128,
+        limit_price: U128,
+        fee_bps: Option<u32>,
+    ) -> u64 {
+        if amount.0 == 0 || limit_price.0 == 0 {
+            fail(AmmError::InvalidLimitOrder)
+        }
+        let pool_id = self.internal_find_pool(&sell_token, &buy_token, fee_bps);
+        let mut pool = self.internal_load_pool(pool_id);
+        let account_id = env::predecessor_account_id();
+
+        // The buy token entry is opened in advance, so fills don't need more storage
+        let mut account = self.internal_unwrap_account(&account_id);
+        account.withdraw(&sell_token, amount.0);
+        account.deposit(&buy_token, 0);
+
+        let order_id = self.next_order_id;
+        self.next_order_id += 1;
+        let mut order = LimitOrder {
+            account_id: account_id.clone(),
+            pool_id,
+            sell_token_id: sell_token.clone(),
+            buy_token_id: buy_token,
+            amount: amount.0,
+            bought: 0,
+            limit_price: limit_price.0,
+            storage_usage: 0,
+        };
+        let initial_storage_usage = env::storage_usage();
+        let sell_index = pool.token_index(&sell_token);
+        pool.order_books[sell_index].insert(&(limit_price.0, order_id), &());
+        self.limit_orders.insert(&order_id, &order);
+        let mut order_ids = self.account_orders.get(&account_id).unwrap_or_default();
+        order_ids.push(order_id);
+        self.account_orders.insert(&account_id, &order_ids);
+        order.storage_usage = env::storage_usage() - initial_storage_usage;
+        self.limit_orders.insert(&order_id, &order);
+        account.storage_used += order.storage_usage;
+        self.internal_save_account(&account_id, &account);
+
+        // The order is filled at once if the pool price is already better than the limit
+        self.internal_fill_limit_orders(pool_id, &mut pool, env::block_timestamp());
+        self.internal_save_pool(pool_id, &pool);
+        order_id
+    }
+
+    // Closes the order and returns unsold tokens to the deposit of the maker
+    pub fn cancel_limit_order(&mut self, order_id: u64) -> U128 {
+        let order = self.internal_unwrap_limit_order(order_id);
+        if order.account_id != env::predecessor_account_id() {
+            fail(AmmError::NotLimitOrderMaker)
+        }
+        let mut pool = self.internal_unwrap_pool(order.pool_id);
+        self.internal_remove_limit_order(&mut pool, order_id, &order);
+        self.internal_save_pool(order.pool_id, &pool);
+        U128::from(order.amount)
+    }
+
+    pub fn get_limit_order(&self, order_id: u64) -> Option<LimitOrderView> {
+        self.limit_orders
+            .get(&order_id)
+            .map(|order| LimitOrderView::new(order_id, order))
+    }
+
+    // Open orders of the maker in the order of placement
+    pub fn get_account_limit_orders(
+        &self,
+        account_id: AccountId,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<LimitOrderView> {
+        self.account_orders
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|order_id| {
+                LimitOrderView::new(order_id, self.internal_unwrap_limit_order(order_id))
+            })
+            .collect()
+    }
+
+    // Open orders of the pool by sold token, each side ordered by limit price
+    pub fn get_pool_limit_orders(
+        &self,
+        pool_id: u64,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<LimitOrderView> {
+        let pool = self.internal_unwrap_pool(pool_id);
+        pool.order_books
+            .iter()
+            .flat_map(|order_book| order_book.iter().map(|(key, _)| key.1))
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|order_id| {
+                LimitOrderView::new(order_id, self.internal_unwrap_limit_order(order_id))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,220 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance, Promise, StorageUsage};
+
+use crate::admin::AdminAction;
+use crate::utils::{charge_storage, now_sec};
+use crate::*;
+
+// With the multisig owner actions are applied only when `threshold` of members confirm them
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MultisigConfig {
+    pub members: Vec<AccountId>,
+    pub threshold: u32,
+    // Requests not confirmed in this time are expired
+    pub request_ttl_sec: u64,
+}
+
+impl MultisigConfig {
+    pub fn assert_valid(&self) {
+        if self.threshold == 0 || self.threshold as usize > self.members.len() {
+            fail_with(
+                AmmError::InvalidMultisigConfig,
+                "threshold must be between 1 and the number of members",
+            )
+        }
+        for (index, member) in self.members.iter().enumerate() {
+            if self.members[..index].contains(member) {
+                fail_with(AmmError::InvalidMultisigConfig, "members must be unique")
+            }
+        }
+        if self.request_ttl_sec == 0 {
+            fail_with(
+                AmmError::InvalidMultisigConfig,
+                "request TTL must be greater than zero",
+            )
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct MultisigRequest {
+    pub action: AdminAction,
+    pub confirmations: Vec<AccountId>,
+    // Time in seconds
+    pub expires_at: u64,
+    // Storage of the record is paid by the creator and returned when the request is removed
+    pub creator_id: AccountId,
+    pub storage_usage: StorageUsage,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MultisigRequestView {
+    pub request_id: u64,
+    pub action: AdminAction,
+    pub confirmations: Vec<AccountId>,
+    pub expires_at: u64,
+}
+
+impl AMM {
+    fn internal_unwrap_multisig(&self) -> MultisigConfig {
+        self.multisig.clone().or_fail(AmmError::MultisigNotEnabled)
+    }
+
+    fn assert_multisig_member(&self, config: &MultisigConfig, account_id: &AccountId) {
+        if self.governance.is_some() {
+            fail(AmmError::OwnerDisabledByGovernance)
+        }
+        if !config.members.contains(account_id) {
+            fail(AmmError::NotMultisigMember)
+        }
+    }
+
+    fn internal_remove_multisig_request(&mut self, request_id: u64) -> MultisigRequest {
+        let request = self
+            .multisig_requests
+            .remove(&request_id)
+            .or_fail(AmmError::RequestNotFound);
+        Promise::new(request.creator_id.clone())
+            .transfer(Balance::from(request.storage_usage) * env::storage_byte_cost());
+        request
+    }
+
+    // Confirms the request by the member and executes it when it has enough confirmations
+    // of current members. With the timelock delay the action is queued instead, its storage
+    // is paid from the storage deposit of the request
+    fn internal_confirm_multisig_request(
+        &mut self,
+        config: &MultisigConfig,
+        request_id: u64,
+        mut request: MultisigRequest,
+        account_id: AccountId,
+    ) {
+        if now_sec() >= request.expires_at {
+            fail(AmmError::RequestExpired)
+        }
+        if request.confirmations.contains(&account_id) {
+            fail(AmmError::AlreadyConfirmed)
+        }
+        request.confirmations.push(account_id);
+        let confirmations = request
+            .confirmations
+            .iter()
+            .filter(|member| config.members.contains(member))
+            .count();
+        if confirmations < config.threshold as usize {
+            self.multisig_requests.insert(&request_id, &request);
+            return;
+        }
+        let request = self
+            .multisig_requests
+            .remove(&request_id)
+            .or_fail(AmmError::RequestNotFound);
+        let mut refund = request.storage_usage;
+        if self.timelock.delay_sec > 0 {
+            let (action_id, storage_usage) =
+                self.internal_queue_action(request.action, request.creator_id.clone(), true);
+            refund = refund.saturating_sub(storage_usage);
+            log!(
+                "Queue multisig request {} as action {}",
+                request_id,
+                action_id
+            );
+        } else {
+            log!("Execute multisig request {}", request_id);
+            self.internal_execute_admin_action(request.action);
+        }
+        Promise::new(request.creator_id).transfer(Balance::from(refund) * env::storage_byte_cost());
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Hands owner actions over to the multisig, after that its config is changed by requests only
+    #[payable]
+    pub fn set_multisig(&mut self, config: MultisigConfig) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::SetMultisig { config });
+    }
+
+    pub fn get_multisig(&self) -> Option<MultisigConfig> {
+        self.multisig.clone()
+    }
+
+    // Creates the request confirmed by the creator, the storage of the request is paid
+    // by the attached deposit
+    #[payable]
+    pub fn create_multisig_request(&mut self, action: AdminAction) -> u64 {
+        let config = self.internal_unwrap_multisig();
+        let account_id = env::predecessor_account_id();
+        self.assert_multisig_member(&config, &account_id);
+        let request_id = self.next_multisig_request_id;
+        self.next_multisig_request_id += 1;
+        let initial_storage_usage = env::storage_usage();
+        let mut request = MultisigRequest {
+            action,
+            confirmations: vec![],
+            expires_at: now_sec() + config.request_ttl_sec,
+            creator_id: account_id.clone(),
+            storage_usage: 0,
+        };
+        self.multisig_requests.insert(&request_id, &request);
+        request.storage_usage = env::storage_usage() - initial_storage_usage;
+        self.multisig_requests.insert(&request_id, &request);
+        charge_storage(initial_storage_usage);
+        self.internal_confirm_multisig_request(&config, request_id, request, account_id);
+        request_id
+    }
+
+    #[payable]
+    pub fn confirm_multisig_request(&mut self, request_id: u64) {
+        assert_one_yocto();
+        let config = self.internal_unwrap_multisig();
+        let account_id = env::predecessor_account_id();
+        self.assert_multisig_member(&config, &account_id);
+        let request = self
+            .multisig_requests
+            .get(&request_id)
+            .or_fail(AmmError::RequestNotFound);
+        self.internal_confirm_multisig_request(&config, request_id, request, account_id);
+    }
+
+    // Removes the expired request and returns its storage deposit to the creator, anyone can call it
+    pub fn remove_expired_multisig_request(&mut self, request_id: u64) {
+        let request = self
+            .multisig_requests
+            .get(&request_id)
+            .or_fail(AmmError::RequestNotFound);
+        if now_sec() < request.expires_at {
+            fail(AmmError::RequestNotExpired)
+        }
+        self.internal_remove_multisig_request(request_id);
+    }
+
+    pub fn get_multisig_request(&self, request_id: u64) -> Option<MultisigRequestView> {
+        self.multisig_requests
+            .get(&request_id)
+            .map(|request| MultisigRequestView {
+                request_id,
+                action: request.action,
+                confirmations: request.confirmations,
+                expires_at: request.expires_at,
+            })
+    }
+
+    pub fn get_multisig_requests(&self, from_index: u64, limit: u64) -> Vec<MultisigRequestView> {
+        self.multisig_requests
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(request_id, request)| MultisigRequestView {
+                request_id,
+                action: request.action,
+                confirmations: request.confirmations,
+                expires_at: request.expires_at,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Gas, Promise, PromiseResult};
+
+use crate::pool::{FEE_DIVISOR, FEE_TIERS};
+use crate::*;
+
+impl AMM {
+    // Owner controls AMM until governance or the multisig is enabled
+    pub(crate) fn assert_owner_account(&self) {
+        if env::predecessor_account_id() != self.owner_id {
+            fail(AmmError::NotOwner)
+        }
+        if self.governance.is_some() {
+            fail(AmmError::OwnerDisabledByGovernance)
+        }
+        if self.multisig.is_some() {
+            fail(AmmError::MultisigRequired)
+        }
+    }
+
+    // Owner methods apply changes immediately only without the timelock delay
+    pub(crate) fn assert_owner(&self) {
+        assert_one_yocto();
+        self.assert_owner_account();
+        if self.timelock.delay_sec > 0 {
+            fail(AmmError::TimelockRequired)
+        }
+    }
+
+    pub(crate) fn internal_list_token(
+        &mut self,
+        token_id: AccountId,
+        metadata: FungibleTokenMetadata,
+    ) {
+        if self.tokens.contains_key(&token_id) {
+            fail(AmmError::TokenAlreadyListed)
+        }
+        self.tokens.insert(&token_id, &metadata);
+    }
+
+    pub(crate) fn internal_set_fee(&mut self, pool_id: u64, fee_bps: u32) {
+        if !FEE_TIERS.contains(&fee_bps) {
+            fail(AmmError::FeeTierNotSupported)
+        }
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        let key = pool_key(&pool.token_ids[0], &pool.token_ids[1], fee_bps);
+        if self.pool_ids.contains_key(&key) {
+            fail(AmmError::PoolAlreadyExists)
+        }
+        self.pool_ids.remove(&pool_key(
+            &pool.token_ids[0],
+            &pool.token_ids[1],
+            pool.fee_bps,
+        ));
+        self.pool_ids.insert(&key, &pool_id);
+        pool.fee_bps = fee_bps;
+        self.internal_save_pool(pool_id, &pool);
+    }
+
+    pub(crate) fn internal_set_dynamic_fee(
+        &mut self,
+        pool_id: u64,
+        config: Option<DynamicFeeConfig>,
+    ) {
+        if let Some(config) = &config {
+            config.assert_valid();
+        }
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        pool.dynamic_fee = config;
+        pool.volatility = Default::default();
+        self.internal_save_pool(pool_id, &pool);
+    }
+
+    pub(crate) fn internal_set_max_price_impact(
+        &mut self,
+        pool_id: u64,
+        max_price_impact_bps: Option<u32>,
+    ) {
+        if matches!(max_price_impact_bps, Some(bps) if bps > FEE_DIVISOR) {
+            fail_with(AmmError::BasisPointsTooLarge, "price impact")
+        }
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        pool.max_price_impact_bps = max_price_impact_bps;
+        self.internal_save_pool(pool_id, &pool);
+    }
+
+    pub(crate) fn internal_set_protocol_fee(&mut self, pool_id: u64, protocol_fee_bps: u32) {
+        if protocol_fee_bps > FEE_DIVISOR {
+            fail_with(AmmError::BasisPointsTooLarge, "protocol fee")
+        }
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        pool.protocol_fee_bps = protocol_fee_bps;
+        self.internal_save_pool(pool_id, &pool);
+    }
+
+    // Fees in flight stay held until the callback, so skim never takes them
+    pub(crate) fn internal_claim_protocol_fees(&mut self, pool_id: u64) {
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        for index in 0..pool.token_ids.len() {
+            let amount = pool.protocol_fees[index];
+            if amount == 0 {
+                continue;
+            }
+            pool.protocol_fees[index] = 0;
+            let token_id = pool.token_ids[index].clone();
+            log!("Claim {} of {} protocol fees", amount, token_id);
+            let transfer = if token_id == near_token_id() {
+                Promise::new(self.treasury_id.clone()).transfer(amount)
+            } else {
+                ext_ft::ft_transfer(
+                    self.treasury_id.clone(),
+                    U128::from(amount),
+                    Some("Protocol fees".to_string()),
+                    token_id.clone(),
+                    1,
+                    Gas::from(5_000_000_000_000),
+                )
+            };
+            transfer.then(ext_self::on_claim_protocol_fee(
+                pool_id,
+                token_id,
+                U128::from(amount),
+                env::current_account_id(),
+                0,
+                Gas::from(5_000_000_000_000),
+            ));
+        }
+        self.internal_save_pool(pool_id, &pool);
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    pub fn get_treasury(&self) -> AccountId {
+        self.treasury_id.clone()
+    }
+
+    #[payable]
+    pub fn set_treasury(&mut self, treasury_id: AccountId) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::SetTreasury { treasury_id });
+    }
+
+    // Adds the token to the list of tokens allowed in pools
+    #[payable]
+    pub fn list_token(&mut self, token_id: AccountId, metadata: FungibleTokenMetadata) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::ListToken { token_id, metadata });
+    }
+
+    // Creates an empty pool of listed tokens with one of `FEE_TIERS`
+    #[payable]
+    pub fn add_pool(&mut self, token_a: AccountId, token_b: AccountId, fee_bps: u32) -> u64 {
+        self.assert_owner();
+        self.internal_add_pool(token_a, token_b, fee_bps)
+    }
+
+    // Moves the pool to another of `FEE_TIERS`, the pair can't have two pools in a tier
+    #[payable]
+    pub fn set_fee(&mut self, pool_id: u64, fee_bps: u32) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::SetFee { pool_id, fee_bps });
+    }
+
+    // Switches the pool to the fee driven by recent volatility, `None` returns the fee tier
+    #[payable]
+    pub fn set_dynamic_fee(&mut self, pool_id: u64, config: Option<DynamicFeeConfig>) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::SetDynamicFee { pool_id, config });
+    }
+
+    // Limits the price impact of a single swap in the pool (in basis points), `None` removes the limit
+    #[payable]
+    pub fn set_max_price_impact(&mut self, pool_id: u64, max_price_impact_bps: Option<u32>) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::SetMaxPriceImpact {
+            pool_id,
+            max_price_impact_bps,
+        });
+    }
+
+    // Sets the part of every swap fee in the pool (in basis points of the fee)
+    // that goes to the treasury
+    #[payable]
+    pub fn set_protocol_fee(&mut self, pool_id: u64, protocol_fee_bps: u32) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::SetProtocolFee {
+            pool_id,
+            protocol_fee_bps,
+        });
+    }
+
+    // Protocol fees of the pool accrued by token and not claimed yet
+    pub fn get_protocol_fees(&self, pool_id: u64) -> HashMap<AccountId, U128> {
+        let pool = self.internal_unwrap_pool(pool_id);
+        pool.token_ids
+            .iter()
+            .cloned()
+            .zip(pool.protocol_fees.iter().map(|fee| U128::from(*fee)))
+            .collect()
+    }
+
+    // Sends all accrued protocol fees of the pool to the treasury
+    #[payable]
+    pub fn claim_protocol_fees(&mut self, pool_id: u64) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::ClaimProtocolFees { pool_id });
+    }
+
+    // Releases claimed fees from the held tokens, or returns them to the pool
+    // if the transfer to the treasury failed
+    #[private]
+    pub fn on_claim_protocol_fee(&mut self, pool_id: u64, token_id: AccountId, amount: U128) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            self.internal_remove_held(&token_id, amount.0);
+            return;
+        }
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        let index = pool.token_index(&token_id);
+        pool.protocol_fees[index] += amount.0;
+        self.internal_save_pool(pool_id, &pool);
+        log!("Failed to claim {} of {} protocol fees", amount.0, token_id);
+    }
+}
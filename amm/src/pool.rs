@@ -0,0 +1,272 @@
+use std::cmp::max;
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, TreeMap, UnorderedMap};
+use near_sdk::{env, AccountId, Balance, BlockHeight, StorageUsage, Timestamp};
+
+use crate::dynamic_fee::{DynamicFeeConfig, Volatility};
+use crate::errors::{fail, AmmError, OrFail};
+use crate::positions::Position;
+use crate::stats::PoolStats;
+use crate::twamm::TwammSide;
+use crate::utils::{add_decimals, calc_dy, mul_div, remove_decimals, U256};
+
+// Fees are set in basis points
+pub const FEE_DIVISOR: u32 = 10_000;
+
+// Fee tiers allowed for pools, several pools of the same pair differ by the fee tier
+pub const FEE_TIERS: [u32; 4] = [1, 5, 30, 100];
+
+// Fee tier of the pool created on initialization and used when the fee tier is not specified
+pub const DEFAULT_FEE_BPS: u32 = 30;
+
+// Shares locked forever by the first liquidity of the pool, so the price of a share
+// can't be inflated by a tiny first deposit and a donation
+pub const MIN_LIQUIDITY: Balance = 1_000;
+
+// Pool of two tokens with its own reserves, LP shares and fee tier
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Pool {
+    pub token_ids: Vec<AccountId>,
+
+    // Decimals of the tokens from their metadata
+    pub decimals: Vec<u8>,
+
+    // Amounts of tokens in the pool
+    pub reserves: Vec<Balance>,
+
+    // Fee charged from the sell amount of every swap, it stays in reserves for LPs
+    pub fee_bps: u32,
+
+    // If set, the fee follows the recent volatility instead of `fee_bps`
+    pub dynamic_fee: Option<DynamicFeeConfig>,
+    pub volatility: Volatility,
+
+    // If set, swaps moving the spot price by more than this (in basis points) are rejected
+    pub max_price_impact_bps: Option<u32>,
+
+    // Volumes, fees and the number of swaps
+    pub stats: PoolStats,
+
+    // Part of the swap fee (in basis points of the fee) that goes to the treasury
+    pub protocol_fee_bps: u32,
+
+    // Accrued protocol fees by token, they are not included to reserves
+    pub protocol_fees: Vec<Balance>,
+
+    // Here the proportions of the investment in the pool are stored
+    pub shares: UnorderedMap<AccountId, Balance>,
+    pub shares_total_supply: Balance,
+
+    // Entry points of LP shares, they are kept with share records
+    pub positions: LookupMap<AccountId, Position>,
+
+    // Ids of limit orders by the index of the sold token, ordered by limit price
+    pub order_books: Vec<TreeMap<(u128, u64), ()>>,
+
+    // Long-running orders by the index of the sold token and the last block they are executed at
+    pub twamm: Vec<TwammSide>,
+    pub twamm_executed_block: BlockHeight,
+}
+
+impl Pool {
+    pub fn new(pool_id: u64, token_ids: Vec<AccountId>, decimals: Vec<u8>, fee_bps: u32) -> Self {
+        Self {
+            token_ids,
+            decimals,
+            reserves: vec![0, 0],
+            fee_bps,
+            dynamic_fee: None,
+            volatility: Volatility::default(),
+            max_price_impact_bps: None,
+            stats: PoolStats::new(),
+            protocol_fee_bps: 0,
+            protocol_fees: vec![0, 0],
+            shares: UnorderedMap::new(format!("s{}", pool_id).into_bytes()),
+            shares_total_supply: 0,
+            positions: LookupMap::new(format!("y{}", pool_id).into_bytes()),
+            order_books: (0..2)
+                .map(|index| TreeMap::new(format!("b{}:{}", pool_id, index).into_bytes()))
+                .collect(),
+            twamm: (0..2)
+                .map(|index| TwammSide::new(format!("w{}:{}", pool_id, index).into_bytes()))
+                .collect(),
+            twamm_executed_block: 0,
+        }
+    }
+
+    pub fn token_index(&self, token_id: &AccountId) -> usize {
+        self.token_ids
+            .iter()
+            .position(|id| id == token_id)
+            .or_fail(AmmError::TokenNotSupported)
+    }
+
+    pub fn shares_of(&self, account_id: &AccountId) -> Balance {
+        self.shares.get(account_id).unwrap_or(0)
+    }
+
+    // Fee of the swap at the moment
+    pub fn current_fee_bps(&self, now: Timestamp) -> u32 {
+        match &self.dynamic_fee {
+            Some(config) => config.fee_bps(self.volatility.value(config, now)),
+            None => self.fee_bps,
+        }
+    }
+
+    // Splits the sell amount into the total swap fee and its protocol part
+    pub fn calc_fees(&self, sell_amount: Balance, fee_bps: u32) -> (Balance, Balance) {
+        let fee = sell_amount * Balance::from(fee_bps) / Balance::from(FEE_DIVISOR);
+        let protocol_fee = fee * Balance::from(self.protocol_fee_bps) / Balance::from(FEE_DIVISOR);
+        (fee, protocol_fee)
+    }
+
+    // Amount of tokens bought for the sell amount left after the fee
+    pub fn calc_buy_amount(&self, sell_index: usize, sell_amount: Balance) -> Balance {
+        let buy_index = 1 - sell_index;
+
+        // Convert to the same decimal
+        let max_decimals = max(self.decimals[0], self.decimals[1]);
+        let sell_decimals = max_decimals - self.decimals[sell_index];
+        let buy_decimals = max_decimals - self.decimals[buy_index];
+        let x = add_decimals(self.reserves[sell_index], sell_decimals);
+        let y = add_decimals(self.reserves[buy_index], buy_decimals);
+        let dx = add_decimals(sell_amount, sell_decimals);
+
+        // Calc buy amount and restore decimal
+        remove_decimals(calc_dy(x, y, dx), buy_decimals)
+    }
+
+    // Fall of the spot price of the sold token made by the swap in basis points. The price is
+    // y / x before and (y - dy) / (x + dx) after, so the impact is 1 - (y - dy) * x / (y * (x + dx))
+    pub fn price_impact_bps(
+        &self,
+        sell_index: usize,
+        sell_amount: Balance,
+        buy_amount: Balance,
+    ) -> u128 {
+        let x = self.reserves[sell_index];
+        let y = self.reserves[1 - sell_index];
+        let before = U256::from(y) * U256::from(x + sell_amount);
+        if before.is_zero() {
+            return 0;
+        }
+        let after = U256::from(y - buy_amount) * U256::from(x);
+        ((before - after) * U256::from(FEE_DIVISOR) / before).as_u128()
+    }
+
+    // Moves tokens of the swap through the reserves, the protocol fee is kept aside
+    pub fn apply_swap(
+        &mut self,
+        sell_index: usize,
+        sell_amount: Balance,
+        buy_amount: Balance,
+        protocol_fee: Balance,
+    ) {
+        self.reserves[sell_index] += sell_amount - protocol_fee;
+        self.reserves[1 - sell_index] -= buy_amount;
+        self.protocol_fees[sell_index] += protocol_fee;
+    }
+
+    // Records the price change made by the swap to the volatility of the pool
+    pub fn observe_price(&mut self, reserves_before: &[Balance], now: Timestamp) {
+        let config = match &self.dynamic_fee {
+            Some(config) => config,
+            None => return,
+        };
+        if reserves_before.contains(&0) || self.reserves.contains(&0) {
+            return;
+        }
+
+        // Price is reserves[1] / reserves[0], so the change is compared by cross products
+        let price_before = U256::from(reserves_before[1]) * U256::from(self.reserves[0]);
+        let price_after = U256::from(self.reserves[1]) * U256::from(reserves_before[0]);
+        let change = if price_after > price_before {
+            price_after - price_before
+        } else {
+            price_before - price_after
+        };
+        let price_change_bps = (change * U256::from(FEE_DIVISOR) / price_before).as_u128();
+        self.volatility.observe(config, now, price_change_bps);
+    }
+
+    // Adding tokens to the pool reserves.
+    // Tokens can only be added in proportion to the amount in the pool
+    pub fn add_liquidity(&mut self, account_id: &AccountId, amounts: &[Balance]) -> Balance {
+        if U256::from(self.reserves[0]) * U256::from(amounts[1])
+            != U256::from(self.reserves[1]) * U256::from(amounts[0])
+        {
+            fail(AmmError::IncorrectProportions)
+        }
+
+        // First liquidity is measured by the sum of tokens in the same decimal without
+        // `MIN_LIQUIDITY` locked shares, next ones get shares in proportion to the pool reserves
+        let (shares, locked_shares) = if self.shares_total_supply == 0 {
+            // Proportions of empty reserves accept any amounts, but a zero reserve
+            // would break prices and share calculations of the pool
+            if amounts.contains(&0) {
+                fail(AmmError::ZeroInitialLiquidity)
+            }
+            let max_decimals = max(self.decimals[0], self.decimals[1]);
+            let shares = add_decimals(amounts[0], max_decimals - self.decimals[0])
+                + add_decimals(amounts[1], max_decimals - self.decimals[1]);
+            (shares.saturating_sub(MIN_LIQUIDITY), MIN_LIQUIDITY)
+        } else {
+            (
+                mul_div(amounts[0], self.shares_total_supply, self.reserves[0]),
+                0,
+            )
+        };
+        if shares == 0 {
+            fail(AmmError::LiquidityTooSmall)
+        }
+
+        self.reserves[0] += amounts[0];
+        self.reserves[1] += amounts[1];
+        self.shares
+            .insert(account_id, &(self.shares_of(account_id) + shares));
+        self.shares_total_supply += shares + locked_shares;
+        let current = self.position_per_share();
+        self.update_position(account_id, shares, &current);
+        shares
+    }
+
+    // Excluding shares from the pool, returns tokens for these shares in the right proportion
+    pub fn remove_liquidity(&mut self, account_id: &AccountId, shares: Balance) -> Vec<Balance> {
+        if self.shares_total_supply == 0 {
+            fail(AmmError::PoolHasNoLiquidity)
+        }
+        let account_shares = self.shares_of(account_id);
+        if shares > account_shares {
+            fail(AmmError::NotEnoughShares)
+        }
+        let amounts: Vec<Balance> = self
+            .reserves
+            .iter()
+            .map(|reserve| mul_div(shares, *reserve, self.shares_total_supply))
+            .collect();
+        self.reserves[0] -= amounts[0];
+        self.reserves[1] -= amounts[1];
+        self.shares.insert(account_id, &(account_shares - shares));
+        self.shares_total_supply -= shares;
+        amounts
+    }
+
+    // Opens the share record and the position of the account, returns bytes taken by them.
+    // The position starts from the current values of the pool
+    pub fn register_account(&mut self, account_id: &AccountId) -> StorageUsage {
+        let initial_storage_usage = env::storage_usage();
+        self.shares.insert(account_id, &0);
+        self.positions
+            .insert(account_id, &self.position_per_share());
+        env::storage_usage() - initial_storage_usage
+    }
+
+    // Closes the share record of the account, returns released bytes
+    pub fn unregister_account(&mut self, account_id: &AccountId) -> StorageUsage {
+        let initial_storage_usage = env::storage_usage();
+        self.shares.remove(account_id);
+        self.positions.remove(account_id);
+        initial_storage_usage - env::storage_usage()
+    }
+}
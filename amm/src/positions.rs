@@ -0,0 +1,180 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{near_bindgen, AccountId, Balance};
+
+use crate::pool::FEE_DIVISOR;
+use crate::utils::U256;
+use crate::*;
+
+// Precision of values per share
+const POSITION_PRECISION: u128 = 1_000_000_000_000_000_000_000_000;
+
+// Entry point of LP shares of the account, it's averaged by shares over all additions
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct Position {
+    // Reserves per share multiplied by `POSITION_PRECISION`
+    pub reserves_per_share: Vec<U256>,
+    // sqrt(x * y) per share multiplied by `POSITION_PRECISION`. Swaps keep x * y without fees,
+    // so it grows only with fees left in the pool
+    pub liquidity_per_share: U256,
+}
+
+impl Position {
+    // Entry of `shares` joined by `added_shares` with the `added` entry, averaged by shares
+    pub fn merge(&self, shares: Balance, added: &Position, added_shares: Balance) -> Position {
+        let total = U256::from(shares + added_shares);
+        if total.is_zero() {
+            return added.clone();
+        }
+        let (shares, added_shares) = (U256::from(shares), U256::from(added_shares));
+        let average = |entry: U256, added: U256| (entry * shares + added * added_shares) / total;
+        Position {
+            reserves_per_share: self
+                .reserves_per_share
+                .iter()
+                .zip(added.reserves_per_share.iter())
+                .map(|(entry, added)| average(*entry, *added))
+                .collect(),
+            liquidity_per_share: average(self.liquidity_per_share, added.liquidity_per_share),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PositionView {
+    pub pool_id: u64,
+    pub shares: U128,
+    // Tokens the shares can be excluded for now
+    pub amounts: Vec<U128>,
+    // Part of `amounts` earned by swap fees since the entry
+    pub fees: Vec<U128>,
+    // Tokens added for the shares at the entry
+    pub entry_amounts: Vec<U128>,
+    // Loss of `amounts` without fees compared with holding `entry_amounts`,
+    // both valued at the current pool price
+    pub impermanent_loss_bps: u32,
+}
+
+impl Pool {
+    // Values per share of the pool at the moment
+    pub fn position_per_share(&self) -> Position {
+        if self.shares_total_supply == 0 {
+            return Position {
+                reserves_per_share: vec![U256::zero(); self.reserves.len()],
+                liquidity_per_share: U256::zero(),
+            };
+        }
+        let total = U256::from(self.shares_total_supply);
+        let liquidity =
+            (U256::from(self.reserves[0]) * U256::from(self.reserves[1])).integer_sqrt();
+        Position {
+            reserves_per_share: self
+                .reserves
+                .iter()
+                .map(|reserve| U256::from(*reserve) * U256::from(POSITION_PRECISION) / total)
+                .collect(),
+            liquidity_per_share: liquidity * U256::from(POSITION_PRECISION) / total,
+        }
+    }
+
+    // Averages the entry of the account with the entry of the added shares,
+    // e.g. current values for added liquidity or the entry of unstaked shares
+    pub fn update_position(
+        &mut self,
+        account_id: &AccountId,
+        added_shares: Balance,
+        added: &Position,
+    ) {
+        let shares = self.shares_of(account_id);
+        let position = match self.positions.get(account_id) {
+            Some(entry) => entry.merge(shares - added_shares, added, added_shares),
+            None => added.clone(),
+        };
+        self.positions.insert(account_id, &position);
+    }
+}
+
+impl AMM {
+    fn internal_position_view(
+        &self,
+        pool_id: u64,
+        pool: &Pool,
+        account_id: &AccountId,
+        position: Position,
+    ) -> PositionView {
+        let shares = U256::from(pool.shares_of(account_id));
+        let current = pool.position_per_share();
+        let precision = U256::from(POSITION_PRECISION);
+        let amounts: Vec<U256> = current
+            .reserves_per_share
+            .iter()
+            .map(|per_share| shares * *per_share / precision)
+            .collect();
+        // Without fees the position would have less liquidity in the same proportion
+        let amounts_without_fees: Vec<U256> = amounts
+            .iter()
+            .map(|amount| {
+                if current.liquidity_per_share.is_zero() {
+                    *amount
+                } else {
+                    *amount * position.liquidity_per_share / current.liquidity_per_share
+                }
+            })
+            .collect();
+        let entry_amounts: Vec<U256> = position
+            .reserves_per_share
+            .iter()
+            .map(|per_share| shares * *per_share / precision)
+            .collect();
+
+        // Values in the second token at the current price
+        let value = |amounts: &[U256]| {
+            if pool.reserves[0] == 0 {
+                amounts[1]
+            } else {
+                amounts[0] * U256::from(pool.reserves[1]) / U256::from(pool.reserves[0])
+                    + amounts[1]
+            }
+        };
+        let hold_value = value(&entry_amounts);
+        let lp_value = value(&amounts_without_fees);
+        let impermanent_loss_bps = if hold_value > lp_value {
+            ((hold_value - lp_value) * U256::from(FEE_DIVISOR) / hold_value).as_u32()
+        } else {
+            0
+        };
+        PositionView {
+            pool_id,
+            shares: U128::from(shares.as_u128()),
+            amounts: amounts
+                .iter()
+                .map(|amount| U128::from(amount.as_u128()))
+                .collect(),
+            fees: amounts
+                .iter()
+                .zip(amounts_without_fees.iter())
+                .map(|(amount, without_fees)| U128::from((*amount - *without_fees).as_u128()))
+                .collect(),
+            entry_amounts: entry_amounts
+                .iter()
+                .map(|amount| U128::from(amount.as_u128()))
+                .collect(),
+            impermanent_loss_bps,
+        }
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Position of LP shares of the account in the pool, staked shares are not included
+    // and bring their entry back when unstaked
+    pub fn get_position(&self, account_id: AccountId, pool_id: u64) -> Option<PositionView> {
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        pool.preview_twamm();
+        pool.positions
+            .get(&account_id)
+            .map(|position| self.internal_position_view(pool_id, &pool, &account_id, position))
+    }
+}
@@ -0,0 +1,57 @@
+use near_sdk::{near_bindgen, AccountId, Balance};
+
+use crate::pool::FEE_DIVISOR;
+use crate::*;
+
+impl AMM {
+    // Part of the swap fee for the referrer, it's limited by the fee left after the protocol part
+    pub(crate) fn internal_calc_referral_fee(
+        &self,
+        referral_id: Option<&AccountId>,
+        fee: Balance,
+        protocol_fee: Balance,
+    ) -> Balance {
+        referral_id
+            .and_then(|referral_id| self.referrals.get(referral_id))
+            .map(|referral_fee_bps| {
+                let referral_fee =
+                    fee * Balance::from(referral_fee_bps) / Balance::from(FEE_DIVISOR);
+                referral_fee.min(fee - protocol_fee)
+            })
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn internal_set_referral(&mut self, referral_id: AccountId, referral_fee_bps: u32) {
+        if referral_fee_bps > FEE_DIVISOR {
+            fail_with(AmmError::BasisPointsTooLarge, "referral fee")
+        }
+        if self.internal_get_account(&referral_id).is_none() {
+            fail(AmmError::ReferrerNotRegistered)
+        }
+        self.referrals.insert(&referral_id, &referral_fee_bps);
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Registers the referrer or updates its part of the swap fee (in basis points of the fee).
+    // Referral fees are credited to the deposits of the referrer, so it must be registered
+    #[payable]
+    pub fn set_referral(&mut self, referral_id: AccountId, referral_fee_bps: u32) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::SetReferral {
+            referral_id,
+            referral_fee_bps,
+        });
+    }
+
+    #[payable]
+    pub fn remove_referral(&mut self, referral_id: AccountId) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::RemoveReferral { referral_id });
+    }
+
+    pub fn get_referral_fee(&self, referral_id: AccountId) -> Option<u32> {
+        self.referrals.get(&referral_id)
+    }
+}
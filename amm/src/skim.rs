@@ -0,0 +1,144 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId, Balance, Gas, PromiseResult};
+
+use crate::*;
+
+impl AMM {
+    // Tokens came to AMM for deposits, pools, orders or farms
+    pub(crate) fn internal_add_held(&mut self, token_id: &AccountId, amount: Balance) {
+        let held = self.held_tokens.get(token_id).unwrap_or(0);
+        self.held_tokens.insert(token_id, &(held + amount));
+    }
+
+    // Tokens left AMM or were dropped from the ledger
+    pub(crate) fn internal_remove_held(&mut self, token_id: &AccountId, amount: Balance) {
+        let held = self.held_tokens.get(token_id).unwrap_or(0);
+        self.held_tokens
+            .insert(token_id, &held.saturating_sub(amount));
+    }
+
+    // Tokens on the balance of AMM above the held amount, e.g. sent without `ft_transfer_call`.
+    // The balance already includes tokens of `ft_transfer_call` whose `ft_on_transfer` isn't
+    // executed yet, they can't be told apart from the excess. So the result is capped by
+    // `max_amount` the owner knows to be sent without `ft_transfer_call`.
+    // Outgoing transfers in flight only lower the result, they are still held until callbacks
+    fn internal_excess(&self, token_id: &AccountId, max_amount: Balance) -> Balance {
+        match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                let balance: U128 = serde_json::from_slice(&value)
+                    .ok()
+                    .or_fail(AmmError::BalanceQueryFailed);
+                balance
+                    .0
+                    .saturating_sub(self.held_tokens.get(token_id).unwrap_or(0))
+                    .min(max_amount)
+            }
+            _ => fail_with(AmmError::BalanceQueryFailed, token_id),
+        }
+    }
+
+    pub(crate) fn internal_skim(&mut self, token_id: AccountId, max_amount: U128) {
+        if !self.tokens.contains_key(&token_id) {
+            fail(AmmError::TokenNotSupported);
+        }
+        if token_id == near_token_id() {
+            fail(AmmError::NearBalanceNotReconciled)
+        }
+        ext_ft::ft_balance_of(
+            env::current_account_id(),
+            token_id.clone(),
+            0,
+            Gas::from(5_000_000_000_000),
+        )
+        .then(ext_self::on_skim(
+            token_id,
+            max_amount,
+            env::current_account_id(),
+            0,
+            Gas::from(15_000_000_000_000),
+        ));
+    }
+
+    // NEAR side of the pool can't be synced, its balance also pays for the storage
+    pub(crate) fn internal_sync(&mut self, pool_id: u64, token_id: AccountId, max_amount: U128) {
+        self.internal_unwrap_pool(pool_id).token_index(&token_id);
+        if token_id == near_token_id() {
+            fail(AmmError::NearBalanceNotReconciled)
+        }
+        ext_ft::ft_balance_of(
+            env::current_account_id(),
+            token_id.clone(),
+            0,
+            Gas::from(5_000_000_000_000),
+        )
+        .then(ext_self::on_sync(
+            pool_id,
+            token_id,
+            max_amount,
+            env::current_account_id(),
+            0,
+            Gas::from(10_000_000_000_000),
+        ));
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Sends the excess balance of the token to the treasury, not more than `max_amount`
+    #[payable]
+    pub fn skim(&mut self, token_id: AccountId, max_amount: U128) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::Skim {
+            token_id,
+            max_amount,
+        });
+    }
+
+    // Adds the excess balance of the pool token to its reserves, so it goes to LPs.
+    // Not more than `max_amount` is added
+    #[payable]
+    pub fn sync(&mut self, pool_id: u64, token_id: AccountId, max_amount: U128) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::Sync {
+            pool_id,
+            token_id,
+            max_amount,
+        });
+    }
+
+    #[private]
+    pub fn on_skim(&mut self, token_id: AccountId, max_amount: U128) {
+        let excess = self.internal_excess(&token_id, max_amount.0);
+        if excess == 0 {
+            return;
+        }
+        log!("Skim {} of {} to the treasury", excess, token_id);
+        ext_ft::ft_transfer(
+            self.treasury_id.clone(),
+            U128::from(excess),
+            Some("Skim".to_string()),
+            token_id,
+            1,
+            Gas::from(5_000_000_000_000),
+        );
+    }
+
+    #[private]
+    pub fn on_sync(&mut self, pool_id: u64, token_id: AccountId, max_amount: U128) {
+        let excess = self.internal_excess(&token_id, max_amount.0);
+        if excess == 0 {
+            return;
+        }
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        let index = pool.token_index(&token_id);
+        pool.reserves[index] += excess;
+        self.internal_save_pool(pool_id, &pool);
+        self.internal_add_held(&token_id, excess);
+        log!("Sync {} of {} to pool {}", excess, token_id, pool_id);
+    }
+
+    // Tokens AMM holds for users, the rest of its balance can be skimmed
+    pub fn get_held_balance(&self, token_id: AccountId) -> U128 {
+        U128::from(self.held_tokens.get(&token_id).unwrap_or(0))
+    }
+}
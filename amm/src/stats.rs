@@ -0,0 +1,131 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Balance, Timestamp};
+
+use crate::utils::NANOS_PER_SEC;
+use crate::*;
+
+const NANOS_PER_HOUR: u64 = 60 * 60 * NANOS_PER_SEC;
+
+// Volume history covers the last 24 hours with one bucket per hour
+const HISTORY_HOURS: u64 = 24;
+
+// Volume sold to the pool by token during the hour
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VolumeBucket {
+    // Hours since the unix epoch
+    pub hour: u64,
+    pub volumes: Vec<U128>,
+}
+
+// Counters of swaps through the pool, volumes and fees are in the sold token
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct PoolStats {
+    pub volumes: Vec<Balance>,
+    pub fees: Vec<Balance>,
+    pub swaps: u64,
+
+    // Ring buffer of hourly volumes, the bucket of the hour is at `hour % HISTORY_HOURS`
+    pub history: Vec<VolumeBucket>,
+}
+
+impl PoolStats {
+    pub fn new() -> Self {
+        Self {
+            volumes: vec![0, 0],
+            fees: vec![0, 0],
+            swaps: 0,
+            history: vec![],
+        }
+    }
+
+    pub fn record_swap(
+        &mut self,
+        sell_index: usize,
+        sell_amount: Balance,
+        fee: Balance,
+        now: Timestamp,
+    ) {
+        self.volumes[sell_index] += sell_amount;
+        self.fees[sell_index] += fee;
+        self.swaps += 1;
+
+        // The bucket left from a previous day is reused for the current hour
+        let hour = now / NANOS_PER_HOUR;
+        let index = (hour % HISTORY_HOURS) as usize;
+        if self.history.len() <= index {
+            self.history.resize(
+                index + 1,
+                VolumeBucket {
+                    hour: 0,
+                    volumes: vec![U128::from(0); 2],
+                },
+            );
+        }
+        let bucket = &mut self.history[index];
+        if bucket.hour != hour {
+            bucket.hour = hour;
+            bucket.volumes = vec![U128::from(0); 2];
+        }
+        bucket.volumes[sell_index] = U128::from(bucket.volumes[sell_index].0 + sell_amount);
+    }
+
+    // Buckets of the last 24 hours ordered by time
+    fn recent_history(&self, now: Timestamp) -> Vec<VolumeBucket> {
+        let hour = now / NANOS_PER_HOUR;
+        let mut buckets: Vec<VolumeBucket> = self
+            .history
+            .iter()
+            .filter(|bucket| bucket.hour + HISTORY_HOURS > hour)
+            .cloned()
+            .collect();
+        buckets.sort_by_key(|bucket| bucket.hour);
+        buckets
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolStatsView {
+    pub pool_id: u64,
+    pub token_ids: Vec<AccountId>,
+    // Total volumes and fees by the sold token since the pool creation
+    pub volumes: Vec<U128>,
+    pub fees: Vec<U128>,
+    pub swaps: u64,
+    // Volumes of the last 24 hours in total and by hour
+    pub volumes_24h: Vec<U128>,
+    pub history: Vec<VolumeBucket>,
+}
+
+#[near_bindgen]
+impl AMM {
+    // Swaps and limit order fills are counted, TWAMM execution isn't
+    pub fn get_pool_stats(&self, pool_id: u64) -> PoolStatsView {
+        let pool = self.internal_unwrap_pool(pool_id);
+        let stats = &pool.stats;
+        let history = stats.recent_history(env::block_timestamp());
+        let to_view =
+            |values: &Vec<Balance>| values.iter().map(|value| U128::from(*value)).collect();
+        PoolStatsView {
+            pool_id,
+            token_ids: pool.token_ids.clone(),
+            volumes: to_view(&stats.volumes),
+            fees: to_view(&stats.fees),
+            swaps: stats.swaps,
+            volumes_24h: (0..pool.token_ids.len())
+                .map(|index| {
+                    U128::from(
+                        history
+                            .iter()
+                            .map(|bucket| bucket.volumes[index].0)
+                            .sum::<Balance>(),
+                    )
+                })
+                .collect(),
+            history,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId};
+
+use crate::*;
+
+impl AMM {
+    // Opens the LP share record of the account in the pool.
+    // The storage of the record is charged from the account storage balance
+    pub(crate) fn internal_register_shares(&mut self, pool: &mut Pool, account_id: &AccountId) {
+        if pool.shares.get(account_id).is_some() {
+            return;
+        }
+        let mut account = self.internal_unwrap_account(account_id);
+        account.storage_used += pool.register_account(account_id);
+        self.internal_save_account(account_id, &account);
+    }
+
+    // Closes the empty LP share record of the account in the pool and releases its storage
+    pub(crate) fn internal_unregister_shares(&mut self, pool: &mut Pool, account_id: &AccountId) {
+        if let Some(0) = pool.shares.get(account_id) {
+            let mut account = self.internal_unwrap_account(account_id);
+            account.storage_used -= pool.unregister_account(account_id);
+            self.internal_save_account(account_id, &account);
+        }
+    }
+}
+
+// One storage balance per account covers its deposits in the ledger
+// and its LP share records in pools
+#[near_bindgen]
+impl StorageManagement for AMM {
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        self.ledger_storage_deposit(&account_id, registration_only)
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        self.ledger_storage_withdraw(amount)
+    }
+
+    // Deposits are dropped only with `force`, but liquidity must always be excluded
+    // from all pools and orders must be cancelled before unregistering
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        // Share records are closed when liquidity is excluded and order records when
+        // orders are closed, so any record left means liquidity or open orders
+        if let Some(account) = self.internal_get_account(&account_id) {
+            if account.storage_used > 0 {
+                fail(AmmError::UnregisterWithLiquidity)
+            }
+        }
+        self.ledger_storage_unregister(force)
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        self.ledger_storage_balance_bounds()
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.ledger_storage_balance_of(&account_id)
+    }
+}
@@ -0,0 +1,180 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance, Promise, StorageUsage};
+
+use crate::admin::AdminAction;
+use crate::events;
+use crate::utils::{charge_storage, now_sec};
+use crate::*;
+
+// Queued action can't be executed after this time since its ETA
+const GRACE_PERIOD_SEC: u64 = 14 * 24 * 60 * 60;
+
+// With the delay owner actions are queued and executed only after it, so users can react
+// to them. Guardians can cancel queued actions
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TimelockConfig {
+    pub delay_sec: u64,
+    pub guardians: Vec<AccountId>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct QueuedAction {
+    pub action: AdminAction,
+    // Time in seconds after which the action can be executed
+    pub eta: u64,
+    // Storage of the record is paid by the owner or the creator of the multisig request
+    // and returned when the action is removed
+    pub payer_id: AccountId,
+    pub storage_usage: StorageUsage,
+    // Actions queued by the owner can't be executed after the multisig is enabled
+    pub from_multisig: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QueuedActionView {
+    pub action_id: u64,
+    pub action: AdminAction,
+    pub eta: u64,
+}
+
+impl AMM {
+    // Queues the action to be executed after the delay, returns its id and storage usage
+    pub(crate) fn internal_queue_action(
+        &mut self,
+        action: AdminAction,
+        payer_id: AccountId,
+        from_multisig: bool,
+    ) -> (u64, StorageUsage) {
+        let action_id = self.next_action_id;
+        self.next_action_id += 1;
+        let eta = now_sec() + self.timelock.delay_sec;
+        let kind = action.kind();
+        let initial_storage_usage = env::storage_usage();
+        let mut queued = QueuedAction {
+            action,
+            eta,
+            payer_id,
+            storage_usage: 0,
+            from_multisig,
+        };
+        self.queued_actions.insert(&action_id, &queued);
+        queued.storage_usage = env::storage_usage() - initial_storage_usage;
+        self.queued_actions.insert(&action_id, &queued);
+        events::AdminActionQueued {
+            action_id,
+            kind,
+            eta,
+        }
+        .emit();
+        (action_id, queued.storage_usage)
+    }
+
+    // Removes the queued action and returns its storage deposit to the payer
+    fn internal_remove_queued_action(&mut self, action_id: u64) -> QueuedAction {
+        let queued = self
+            .queued_actions
+            .remove(&action_id)
+            .or_fail(AmmError::ActionNotFound);
+        Promise::new(queued.payer_id.clone())
+            .transfer(Balance::from(queued.storage_usage) * env::storage_byte_cost());
+        queued
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Sets the delay of owner actions and guardians while there is no delay yet,
+    // after that the timelock is changed by queued actions only
+    #[payable]
+    pub fn set_timelock(&mut self, config: TimelockConfig) {
+        self.assert_owner();
+        self.internal_execute_admin_action(AdminAction::SetTimelock { config });
+    }
+
+    pub fn get_timelock(&self) -> TimelockConfig {
+        self.timelock.clone()
+    }
+
+    // Queues the owner action to be executed after the delay,
+    // the storage of the action is paid by the attached deposit
+    #[payable]
+    pub fn queue_action(&mut self, action: AdminAction) -> u64 {
+        self.assert_owner_account();
+        let initial_storage_usage = env::storage_usage();
+        let (action_id, _) = self.internal_queue_action(action, self.owner_id.clone(), false);
+        charge_storage(initial_storage_usage);
+        action_id
+    }
+
+    // Applies the queued action after its ETA, anyone can call it
+    pub fn execute_action(&mut self, action_id: u64) {
+        if self.governance.is_some() {
+            fail(AmmError::OwnerDisabledByGovernance)
+        }
+        let queued = self
+            .queued_actions
+            .get(&action_id)
+            .or_fail(AmmError::ActionNotFound);
+        if self.multisig.is_some() && !queued.from_multisig {
+            fail(AmmError::MultisigRequired)
+        }
+        let now = now_sec();
+        if now < queued.eta {
+            fail_with(AmmError::ActionNotReady, format!("eta {}", queued.eta))
+        }
+        if now > queued.eta + GRACE_PERIOD_SEC {
+            fail(AmmError::ActionExpired)
+        }
+        let queued = self.internal_remove_queued_action(action_id);
+        events::AdminActionExecuted {
+            action_id,
+            kind: queued.action.kind(),
+        }
+        .emit();
+        self.internal_execute_admin_action(queued.action);
+    }
+
+    // Guardians drop the queued action
+    #[payable]
+    pub fn cancel_action(&mut self, action_id: u64) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        if !self.timelock.guardians.contains(&account_id) {
+            fail(AmmError::NotGuardian)
+        }
+        let queued = self.internal_remove_queued_action(action_id);
+        events::AdminActionCancelled {
+            action_id,
+            kind: queued.action.kind(),
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    pub fn get_queued_action(&self, action_id: u64) -> Option<QueuedActionView> {
+        self.queued_actions
+            .get(&action_id)
+            .map(|queued| QueuedActionView {
+                action_id,
+                action: queued.action,
+                eta: queued.eta,
+            })
+    }
+
+    // Queued actions which are not executed or cancelled yet
+    pub fn get_queued_actions(&self, from_index: u64, limit: u64) -> Vec<QueuedActionView> {
+        self.queued_actions
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(action_id, queued)| QueuedActionView {
+                action_id,
+                action: queued.action,
+                eta: queued.eta,
+            })
+            .collect()
+    }
+}
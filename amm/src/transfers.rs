@@ -0,0 +1,194 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Balance, Gas, Promise, PromiseResult};
+
+use crate::*;
+
+// Transfer of tokens from the deposit to the wallet waiting for its callback.
+// Tokens stay locked on the account until the transfer is finalised or reverted
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct PendingTransfer {
+    pub account_id: AccountId,
+    pub token_id: AccountId,
+    pub amount: Balance,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingTransferView {
+    pub transfer_id: u64,
+    pub account_id: AccountId,
+    pub token_id: AccountId,
+    pub amount: U128,
+}
+
+impl AMM {
+    // Locks tokens of the deposit until the callback of the transfer
+    fn internal_add_pending_transfer(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: Balance,
+    ) -> u64 {
+        let mut account = self.internal_unwrap_account(account_id);
+        account.lock(token_id, amount);
+        self.internal_save_account(account_id, &account);
+
+        let transfer_id = self.next_transfer_id;
+        self.next_transfer_id += 1;
+        self.pending_transfers.insert(
+            &transfer_id,
+            &PendingTransfer {
+                account_id: account_id.clone(),
+                token_id: token_id.clone(),
+                amount,
+            },
+        );
+        transfer_id
+    }
+
+    // Locks tokens of the deposit and sends them to the wallet of the account.
+    // Locked tokens can't be used by swaps or other transfers until the callback
+    pub(crate) fn internal_start_transfer(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: Balance,
+    ) -> Promise {
+        let transfer_id = self.internal_add_pending_transfer(account_id, token_id, amount);
+        let transfer = if token_id == &near_token_id() {
+            Promise::new(account_id.clone()).transfer(amount)
+        } else {
+            ext_ft::ft_transfer(
+                account_id.clone(),
+                U128::from(amount),
+                None,
+                token_id.clone(),
+                1,
+                Gas::from(5_000_000_000_000),
+            )
+        };
+        transfer.then(ext_self::on_transfer(
+            transfer_id,
+            env::current_account_id(),
+            0,
+            Gas::from(10_000_000_000_000),
+        ))
+    }
+
+    // Same as `internal_start_transfer`, but tokens go to the receiver contract with
+    // `ft_transfer_call` and the part it doesn't use returns to the deposit of the account
+    pub(crate) fn internal_start_transfer_call(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: Balance,
+        receiver_id: AccountId,
+        msg: String,
+    ) -> Promise {
+        if token_id == &near_token_id() {
+            fail(AmmError::NearTransferCall)
+        }
+        let transfer_id = self.internal_add_pending_transfer(account_id, token_id, amount);
+        ext_ft::ft_transfer_call(
+            receiver_id,
+            U128::from(amount),
+            None,
+            msg,
+            token_id.clone(),
+            1,
+            Gas::from(60_000_000_000_000),
+        )
+        .then(ext_self::on_transfer_call(
+            transfer_id,
+            env::current_account_id(),
+            0,
+            Gas::from(10_000_000_000_000),
+        ))
+    }
+
+    // Releases locked tokens of the transfer, `used` of them have left AMM
+    // and the rest returns to the deposit
+    fn internal_finish_transfer(&mut self, transfer_id: u64, used: Balance) {
+        let transfer = self
+            .pending_transfers
+            .remove(&transfer_id)
+            .or_fail(AmmError::TransferNotFound);
+        let mut account = self.internal_unwrap_account(&transfer.account_id);
+        account.unlock(&transfer.token_id, transfer.amount);
+        self.internal_remove_held(&transfer.token_id, used);
+        // Only the refunded part is reported as returned
+        if used < transfer.amount {
+            account.deposit(&transfer.token_id, transfer.amount - used);
+            log!(
+                "{} of {} returned to the deposit of {}",
+                transfer.amount - used,
+                transfer.token_id,
+                transfer.account_id
+            );
+        }
+        self.internal_save_account(&transfer.account_id, &account);
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Finalises the transfer by releasing locked tokens or reverts it by returning them
+    // to the deposit if the transfer failed
+    #[private]
+    pub fn on_transfer(&mut self, transfer_id: u64) -> bool {
+        let success = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        let used = if success {
+            self.pending_transfers
+                .get(&transfer_id)
+                .or_fail(AmmError::TransferNotFound)
+                .amount
+        } else {
+            0
+        };
+        self.internal_finish_transfer(transfer_id, used);
+        success
+    }
+
+    // Finalises `ft_transfer_call` with the amount used by the receiver, which the token
+    // returns after its own resolve. Unused tokens are already back on the balance of AMM.
+    // If the token succeeded with a result that can't be parsed, all tokens are considered
+    // used, so the deposit never gets tokens that may have left AMM
+    #[private]
+    pub fn on_transfer_call(&mut self, transfer_id: u64) -> U128 {
+        let amount = self
+            .pending_transfers
+            .get(&transfer_id)
+            .or_fail(AmmError::TransferNotFound)
+            .amount;
+        let used = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                serde_json::from_slice::<U128>(&value).map_or(amount, |used| used.0.min(amount))
+            }
+            _ => 0,
+        };
+        self.internal_finish_transfer(transfer_id, used);
+        U128::from(used)
+    }
+
+    pub fn get_pending_transfer(&self, transfer_id: u64) -> Option<PendingTransferView> {
+        self.pending_transfers
+            .get(&transfer_id)
+            .map(|transfer| PendingTransferView {
+                transfer_id,
+                account_id: transfer.account_id,
+                token_id: transfer.token_id,
+                amount: U128::from(transfer.amount),
+            })
+    }
+
+    // Tokens of the account in pending transfers
+    pub fn get_locked_balance(&self, account_id: AccountId, token_id: AccountId) -> U128 {
+        U128::from(
+            self.internal_get_account(&account_id)
+                .map(|account| account.locked_of(&token_id))
+                .unwrap_or(0),
+        )
+    }
+}
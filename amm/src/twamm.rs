@@ -0,0 +1,398 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::TreeMap;
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Balance, BlockHeight, StorageUsage, Timestamp};
+
+use crate::utils::U256;
+use crate::*;
+
+// Precision of the proceeds accumulated per unit of sell rate
+const PROCEEDS_PRECISION: u128 = 1_000_000_000_000_000_000_000_000;
+
+// Max number of chunks the blocks between ends of orders are executed by
+const TWAMM_CHUNKS: BlockHeight = 8;
+
+// Side of the pool, end block of its orders and `proceeds_per_rate` at the block
+type TwammEnd = (usize, BlockHeight, U256);
+
+// Long-running orders selling one token of the pool. Orders are executed together
+// by their total sell rate, proceeds are shared in proportion to their sell rates
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TwammSide {
+    // Sum of sell rates (tokens per block) of active orders
+    pub sell_rate: Balance,
+    // Buy tokens received per unit of sell rate, multiplied by `PROCEEDS_PRECISION`
+    pub proceeds_per_rate: U256,
+    // Orders ending at the block
+    pub expiries: TreeMap<BlockHeight, TwammExpiry>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct TwammExpiry {
+    // Sum of sell rates of orders ending at the block
+    pub sell_rate: Balance,
+    // Number of orders ending at the block which are not closed yet
+    pub orders: u32,
+    // Value of `proceeds_per_rate` at the block, it's set when the block is executed
+    pub proceeds_per_rate: U256,
+}
+
+impl TwammSide {
+    pub fn new(prefix: Vec<u8>) -> Self {
+        Self {
+            sell_rate: 0,
+            proceeds_per_rate: U256::zero(),
+            expiries: TreeMap::new(prefix),
+        }
+    }
+
+    // Removes the closed order from the expiry of its end, the expiry is removed with
+    // the last order. Sell rate of an active order is removed from the side as well
+    fn remove_order(&mut self, order: &TwammOrder, active: bool) {
+        let mut expiry = self
+            .expiries
+            .get(&order.end_block)
+            .or_fail(AmmError::TwammOrderEndNotFound);
+        if active {
+            self.sell_rate -= order.sell_rate;
+            expiry.sell_rate -= order.sell_rate;
+        }
+        expiry.orders -= 1;
+        if expiry.orders == 0 {
+            self.expiries.remove(&order.end_block);
+        } else {
+            self.expiries.insert(&order.end_block, &expiry);
+        }
+    }
+}
+
+impl Pool {
+    // Sells to the pool the amount of active orders of the side for the number of blocks
+    fn execute_twamm_side(&mut self, sell_index: usize, blocks: BlockHeight, now: Timestamp) {
+        let sell_rate = self.twamm[sell_index].sell_rate;
+        if sell_rate == 0 || blocks == 0 {
+            return;
+        }
+        let sell_amount = sell_rate * Balance::from(blocks);
+        let (fee, protocol_fee) = self.calc_fees(sell_amount, self.current_fee_bps(now));
+        let buy_amount = self.calc_buy_amount(sell_index, sell_amount - fee);
+        self.apply_swap(sell_index, sell_amount, buy_amount, protocol_fee);
+        self.twamm[sell_index].proceeds_per_rate +=
+            U256::from(buy_amount) * U256::from(PROCEEDS_PRECISION) / U256::from(sell_rate);
+    }
+
+    // Sells to the pool the amounts of all active orders for the number of blocks.
+    // Blocks are split into chunks and sides take turns to sell first in them, so orders
+    // selling both tokens trade at close prices
+    fn execute_twamm_blocks(&mut self, blocks: BlockHeight, now: Timestamp) {
+        let chunks = blocks.min(TWAMM_CHUNKS);
+        for chunk in 0..chunks {
+            let chunk_blocks = blocks / chunks + BlockHeight::from(chunk < blocks % chunks);
+            for i in 0..self.twamm.len() {
+                let sell_index = (i + chunk as usize) % self.twamm.len();
+                self.execute_twamm_side(sell_index, chunk_blocks, now);
+            }
+        }
+    }
+
+    // Executes long-running orders for the blocks passed since the last execution.
+    // Blocks are executed by segments between the ends of orders.
+    // Execution is lazy, so all blocks since the last interaction are sold at the price
+    // of the pool at the moment. A swap right before it moves this price against the orders,
+    // which is the cost of not trading every block: orders of many blocks should be
+    // watched by their makers or be small relative to the pool.
+    // Returns `proceeds_per_rate` at the ends of orders passed by the execution, by side.
+    // They are kept in memory only, `execute_twamm` saves them to expiries
+    fn run_twamm(&mut self, block: BlockHeight, now: Timestamp) -> Vec<TwammEnd> {
+        let mut ends = vec![];
+        while self.twamm_executed_block < block {
+            let executed_block = self.twamm_executed_block;
+            let segment_end = self
+                .twamm
+                .iter()
+                .filter_map(|side| side.expiries.higher(&executed_block))
+                .min()
+                .unwrap_or(block)
+                .min(block);
+            self.execute_twamm_blocks(segment_end - executed_block, now);
+            self.twamm_executed_block = segment_end;
+            for (sell_index, side) in self.twamm.iter_mut().enumerate() {
+                if let Some(expiry) = side.expiries.get(&segment_end) {
+                    side.sell_rate -= expiry.sell_rate;
+                    ends.push((sell_index, segment_end, side.proceeds_per_rate));
+                }
+            }
+        }
+        ends
+    }
+
+    pub fn execute_twamm(&mut self, block: BlockHeight, now: Timestamp) {
+        for (sell_index, end_block, proceeds_per_rate) in self.run_twamm(block, now) {
+            let side = &mut self.twamm[sell_index];
+            let mut expiry = side
+                .expiries
+                .get(&end_block)
+                .or_fail(AmmError::TwammOrderEndNotFound);
+            expiry.proceeds_per_rate = proceeds_per_rate;
+            side.expiries.insert(&end_block, &expiry);
+        }
+    }
+
+    // Executes long-running orders up to the current block in memory only, for views
+    pub fn preview_twamm(&mut self) -> Vec<TwammEnd> {
+        self.run_twamm(env::block_height(), env::block_timestamp())
+    }
+}
+
+// Order selling `sell_rate` tokens per block until `end_block`
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TwammOrder {
+    pub account_id: AccountId,
+    pub pool_id: u64,
+    pub sell_token_id: AccountId,
+    pub buy_token_id: AccountId,
+    pub sell_rate: Balance,
+    pub end_block: BlockHeight,
+    // Value of `proceeds_per_rate` when proceeds were withdrawn last time
+    pub proceeds_per_rate: U256,
+    // Bytes of the order records charged from the maker storage balance
+    pub storage_usage: StorageUsage,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TwammOrderView {
+    pub order_id: u64,
+    pub account_id: AccountId,
+    pub pool_id: u64,
+    pub sell_token_id: AccountId,
+    pub buy_token_id: AccountId,
+    pub sell_rate: U128,
+    pub end_block: BlockHeight,
+    // Proceeds not withdrawn yet, including sells accrued since the last interaction
+    pub proceeds: U128,
+}
+
+// Proceeds of the order since the last withdrawal and the current accumulator of its side.
+// Ends of orders passed by the execution not saved yet are looked up in `ends`
+fn calc_proceeds(pool: &Pool, order: &TwammOrder, ends: &[TwammEnd]) -> (Balance, U256) {
+    let sell_index = pool.token_index(&order.sell_token_id);
+    let side = &pool.twamm[sell_index];
+    let proceeds_per_rate = if pool.twamm_executed_block >= order.end_block {
+        match ends
+            .iter()
+            .find(|(index, end_block, _)| *index == sell_index && *end_block == order.end_block)
+        {
+            Some((_, _, proceeds_per_rate)) => *proceeds_per_rate,
+            None => {
+                side.expiries
+                    .get(&order.end_block)
+                    .or_fail(AmmError::TwammOrderEndNotFound)
+                    .proceeds_per_rate
+            }
+        }
+    } else {
+        side.proceeds_per_rate
+    };
+    let proceeds = U256::from(order.sell_rate) * (proceeds_per_rate - order.proceeds_per_rate)
+        / U256::from(PROCEEDS_PRECISION);
+    (proceeds.as_u128(), proceeds_per_rate)
+}
+
+impl AMM {
+    // Loads the pool for a change, long-running orders are executed up to the current block first
+    pub(crate) fn internal_load_pool(&self, pool_id: u64) -> Pool {
+        let mut pool = self.internal_unwrap_pool(pool_id);
+        pool.execute_twamm(env::block_height(), env::block_timestamp());
+        pool
+    }
+
+    fn internal_unwrap_twamm_order(&self, order_id: u64) -> TwammOrder {
+        self.twamm_orders
+            .get(&order_id)
+            .or_fail(AmmError::TwammOrderNotFound)
+    }
+
+    fn internal_twamm_order_view(&self, order_id: u64, order: TwammOrder) -> TwammOrderView {
+        let mut pool = self.internal_unwrap_pool(order.pool_id);
+        let ends = pool.preview_twamm();
+        let (proceeds, _) = calc_proceeds(&pool, &order, &ends);
+        TwammOrderView {
+            order_id,
+            account_id: order.account_id,
+            pool_id: order.pool_id,
+            sell_token_id: order.sell_token_id,
+            buy_token_id: order.buy_token_id,
+            sell_rate: U128::from(order.sell_rate),
+            end_block: order.end_block,
+            proceeds: U128::from(proceeds),
+        }
+    }
+
+    // Credits proceeds of the order to the maker, the order is closed after its end
+    fn internal_withdraw_twamm_proceeds(
+        &mut self,
+        pool: &mut Pool,
+        order_id: u64,
+        order: &mut TwammOrder,
+    ) -> Balance {
+        let (proceeds, proceeds_per_rate) = calc_proceeds(pool, order, &[]);
+        order.proceeds_per_rate = proceeds_per_rate;
+        let mut account = self.internal_unwrap_account(&order.account_id);
+        account.deposit(&order.buy_token_id, proceeds);
+        if pool.twamm_executed_block >= order.end_block {
+            self.internal_remove_twamm_order(pool, order_id, order, &mut account);
+        } else {
+            self.twamm_orders.insert(&order_id, order);
+        }
+        self.internal_save_account(&order.account_id, &account);
+        proceeds
+    }
+
+    fn internal_remove_twamm_order(
+        &mut self,
+        pool: &mut Pool,
+        order_id: u64,
+        order: &TwammOrder,
+        account: &mut Account,
+    ) {
+        let active = pool.twamm_executed_block < order.end_block;
+        let sell_index = pool.token_index(&order.sell_token_id);
+        pool.twamm[sell_index].remove_order(order, active);
+        self.twamm_orders.remove(&order_id);
+        let mut order_ids = self
+            .account_twamm_orders
+            .get(&order.account_id)
+            .unwrap_or_default();
+        order_ids.retain(|id| *id != order_id);
+        if order_ids.is_empty() {
+            self.account_twamm_orders.remove(&order.account_id);
+        } else {
+            self.account_twamm_orders
+                .insert(&order.account_id, &order_ids);
+        }
+        account.storage_used -= order.storage_usage;
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Sells `amount` of tokens from the deposit evenly over the number of blocks
+    // to the pool with the fee tier (default one if not specified).
+    // The part of the amount not divisible by the number of blocks stays in the deposit
+    pub fn place_twamm_order(
+        &mut self,
+        sell_token: AccountId,
+        buy_token: AccountId,
+        amount: U128,
+        blocks: BlockHeight,
+        fee_bps: Option<u32>,
+    ) -> u64 {
+        if blocks == 0 {
+            fail(AmmError::ZeroBlocks)
+        }
+        let sell_rate = amount.0 / Balance::from(blocks);
+        if sell_rate == 0 {
+            fail(AmmError::TwammAmountTooSmall)
+        }
+        let pool_id = self.internal_find_pool(&sell_token, &buy_token, fee_bps);
+        let mut pool = self.internal_load_pool(pool_id);
+        if pool.reserves.contains(&0) {
+            fail(AmmError::PoolHasNoLiquidity)
+        }
+        let account_id = env::predecessor_account_id();
+
+        // The buy token entry is opened in advance, so proceeds don't need more storage
+        let mut account = self.internal_unwrap_account(&account_id);
+        account.withdraw(&sell_token, sell_rate * Balance::from(blocks));
+        account.deposit(&buy_token, 0);
+
+        let order_id = self.next_twamm_order_id;
+        self.next_twamm_order_id += 1;
+        let sell_index = pool.token_index(&sell_token);
+        let end_block = pool.twamm_executed_block + blocks;
+        let initial_storage_usage = env::storage_usage();
+        let side = &mut pool.twamm[sell_index];
+        side.sell_rate += sell_rate;
+        let mut expiry = side.expiries.get(&end_block).unwrap_or_default();
+        expiry.sell_rate += sell_rate;
+        expiry.orders += 1;
+        side.expiries.insert(&end_block, &expiry);
+        let mut order = TwammOrder {
+            account_id: account_id.clone(),
+            pool_id,
+            sell_token_id: sell_token,
+            buy_token_id: buy_token,
+            sell_rate,
+            end_block,
+            proceeds_per_rate: side.proceeds_per_rate,
+            storage_usage: 0,
+        };
+        self.twamm_orders.insert(&order_id, &order);
+        let mut order_ids = self
+            .account_twamm_orders
+            .get(&account_id)
+            .unwrap_or_default();
+        order_ids.push(order_id);
+        self.account_twamm_orders.insert(&account_id, &order_ids);
+        order.storage_usage = env::storage_usage() - initial_storage_usage;
+        self.twamm_orders.insert(&order_id, &order);
+        account.storage_used += order.storage_usage;
+        self.internal_save_account(&account_id, &account);
+        self.internal_save_pool(pool_id, &pool);
+        order_id
+    }
+
+    // Credits the proceeds executed so far to the deposit of the maker
+    pub fn withdraw_twamm_proceeds(&mut self, order_id: u64) -> U128 {
+        let mut order = self.internal_unwrap_twamm_order(order_id);
+        if order.account_id != env::predecessor_account_id() {
+            fail(AmmError::NotTwammOrderMaker)
+        }
+        let mut pool = self.internal_load_pool(order.pool_id);
+        let proceeds = self.internal_withdraw_twamm_proceeds(&mut pool, order_id, &mut order);
+        self.internal_save_pool(order.pool_id, &pool);
+        U128::from(proceeds)
+    }
+
+    // Stops the order, credits its proceeds and returns unexecuted tokens to the deposit
+    pub fn cancel_twamm_order(&mut self, order_id: u64) -> U128 {
+        let mut order = self.internal_unwrap_twamm_order(order_id);
+        if order.account_id != env::predecessor_account_id() {
+            fail(AmmError::NotTwammOrderMaker)
+        }
+        let mut pool = self.internal_load_pool(order.pool_id);
+        self.internal_withdraw_twamm_proceeds(&mut pool, order_id, &mut order);
+        if pool.twamm_executed_block >= order.end_block {
+            self.internal_save_pool(order.pool_id, &pool);
+            return U128::from(0);
+        }
+
+        let unexecuted =
+            order.sell_rate * Balance::from(order.end_block - pool.twamm_executed_block);
+        let mut account = self.internal_unwrap_account(&order.account_id);
+        account.deposit(&order.sell_token_id, unexecuted);
+        self.internal_remove_twamm_order(&mut pool, order_id, &order, &mut account);
+        self.internal_save_account(&order.account_id, &account);
+        self.internal_save_pool(order.pool_id, &pool);
+        U128::from(unexecuted)
+    }
+
+    pub fn get_twamm_order(&self, order_id: u64) -> Option<TwammOrderView> {
+        self.twamm_orders
+            .get(&order_id)
+            .map(|order| self.internal_twamm_order_view(order_id, order))
+    }
+
+    pub fn get_account_twamm_orders(&self, account_id: AccountId) -> Vec<TwammOrderView> {
+        self.account_twamm_orders
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|order_id| {
+                self.internal_twamm_order_view(order_id, self.internal_unwrap_twamm_order(order_id))
+            })
+            .collect()
+    }
+}
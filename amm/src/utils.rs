@@ -1,3 +1,36 @@
+use near_sdk::{env, Balance, Promise, StorageUsage};
+
+use crate::errors::{fail_with, AmmError};
+
+pub use self::u256::U256;
+
+pub const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+// Code generated by `construct_uint!` doesn't pass clippy
+#[allow(clippy::all)]
+mod u256 {
+    use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+    use std::io;
+    use uint::construct_uint;
+
+    construct_uint! {
+        pub struct U256(4);
+    }
+
+    // Stored as its little-endian words
+    impl BorshSerialize for U256 {
+        fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+            self.0.serialize(writer)
+        }
+    }
+
+    impl BorshDeserialize for U256 {
+        fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+            Ok(Self(<[u64; 4]>::deserialize(buf)?))
+        }
+    }
+}
+
 pub fn add_decimals(value: u128, decimals: u8) -> u128 {
     value * 10_u128.pow(decimals as u32)
 }
@@ -6,6 +39,33 @@ pub fn remove_decimals(value: u128, decimals: u8) -> u128 {
     value / 10_u128.pow(decimals as u32)
 }
 
+// a * b / c without overflow of the intermediate product
+pub fn mul_div(a: u128, b: u128, c: u128) -> u128 {
+    (U256::from(a) * U256::from(b) / U256::from(c)).as_u128()
+}
+
 pub fn calc_dy(x: u128, y: u128, dx: u128) -> u128 {
-    y - (x * y / (x + dx))
+    // y - x * y / (x + dx) rounded in favor of the pool
+    mul_div(y, dx, x + dx)
+}
+
+pub fn now_sec() -> u64 {
+    env::block_timestamp() / NANOS_PER_SEC
+}
+
+// Takes the cost of the storage used since `initial_storage_usage` from the attached deposit,
+// the rest is returned
+pub fn charge_storage(initial_storage_usage: StorageUsage) {
+    let cost = Balance::from(env::storage_usage().saturating_sub(initial_storage_usage))
+        * env::storage_byte_cost();
+    let attached = env::attached_deposit();
+    if attached < cost {
+        fail_with(
+            AmmError::NotEnoughStorageDeposit,
+            format!("required {}", cost),
+        )
+    }
+    if attached > cost {
+        Promise::new(env::predecessor_account_id()).transfer(attached - cost);
+    }
 }
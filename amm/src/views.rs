@@ -0,0 +1,165 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::limit_orders::PRICE_PRECISION;
+use crate::pool::FEE_TIERS;
+use crate::utils::mul_div;
+use crate::*;
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolView {
+    pub pool_id: u64,
+    pub token_ids: Vec<AccountId>,
+    pub fee_bps: u32,
+    pub dynamic_fee: Option<DynamicFeeConfig>,
+    // Fee the next swap would be charged with
+    pub current_fee_bps: u32,
+    pub max_price_impact_bps: Option<u32>,
+    pub reserves: Vec<U128>,
+    pub shares_total_supply: U128,
+}
+
+impl PoolView {
+    fn new(pool_id: u64, pool: &Pool) -> Self {
+        Self {
+            pool_id,
+            token_ids: pool.token_ids.clone(),
+            fee_bps: pool.fee_bps,
+            dynamic_fee: pool.dynamic_fee.clone(),
+            current_fee_bps: pool.current_fee_bps(env::block_timestamp()),
+            max_price_impact_bps: pool.max_price_impact_bps,
+            reserves: pool
+                .reserves
+                .iter()
+                .map(|reserve| U128::from(*reserve))
+                .collect(),
+            shares_total_supply: U128::from(pool.shares_total_supply),
+        }
+    }
+}
+
+// State of the pool with metadata of its tokens for front-ends
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolInfo {
+    pub pool_id: u64,
+    pub token_ids: Vec<AccountId>,
+    pub symbols: Vec<String>,
+    pub decimals: Vec<u8>,
+    pub reserves: Vec<U128>,
+    pub shares_total_supply: U128,
+    pub fee_bps: u32,
+    pub current_fee_bps: u32,
+    // Spot price of every token in the other one multiplied by `PRICE_PRECISION`,
+    // 0 while the pool is empty
+    pub prices: Vec<U128>,
+}
+
+impl AMM {
+    fn internal_pool_info(&self, pool_id: u64, pool: &Pool) -> PoolInfo {
+        let metadata: Vec<FungibleTokenMetadata> = pool
+            .token_ids
+            .iter()
+            .map(|token_id| {
+                self.tokens
+                    .get(token_id)
+                    .or_fail(AmmError::TokenNotSupported)
+            })
+            .collect();
+        PoolInfo {
+            pool_id,
+            token_ids: pool.token_ids.clone(),
+            symbols: metadata.iter().map(|meta| meta.symbol.clone()).collect(),
+            decimals: metadata.iter().map(|meta| meta.decimals).collect(),
+            reserves: pool
+                .reserves
+                .iter()
+                .map(|reserve| U128::from(*reserve))
+                .collect(),
+            shares_total_supply: U128::from(pool.shares_total_supply),
+            fee_bps: pool.fee_bps,
+            current_fee_bps: pool.current_fee_bps(env::block_timestamp()),
+            prices: (0..pool.reserves.len())
+                .map(|index| {
+                    let (reserve, other_reserve) = (pool.reserves[index], pool.reserves[1 - index]);
+                    if reserve == 0 {
+                        U128::from(0)
+                    } else {
+                        U128::from(mul_div(other_reserve, PRICE_PRECISION, reserve))
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[near_bindgen]
+impl AMM {
+    // Id of the pool of tokens with the fee tier (default one if not specified)
+    pub fn get_pool_id(
+        &self,
+        token_a: AccountId,
+        token_b: AccountId,
+        fee_bps: Option<u32>,
+    ) -> Option<u64> {
+        self.internal_get_pool_id(&token_a, &token_b, fee_bps)
+    }
+
+    // Pool views include sells of long-running orders accrued since the last interaction
+    pub fn get_pool(&self, pool_id: u64) -> Option<PoolView> {
+        self.pools.get(pool_id).map(|mut pool| {
+            pool.preview_twamm();
+            PoolView::new(pool_id, &pool)
+        })
+    }
+
+    pub fn get_pool_info(&self, pool_id: u64) -> Option<PoolInfo> {
+        self.pools.get(pool_id).map(|mut pool| {
+            pool.preview_twamm();
+            self.internal_pool_info(pool_id, &pool)
+        })
+    }
+
+    // Pools in the order of creation
+    pub fn get_pools(&self, from_index: u64, limit: u64) -> Vec<PoolInfo> {
+        (from_index..self.pools.len().min(from_index.saturating_add(limit)))
+            .filter_map(|pool_id| self.get_pool_info(pool_id))
+            .collect()
+    }
+
+    // All pools of the pair ordered by fee tier, so routers can choose the best one
+    pub fn get_pair_pools(&self, token_a: AccountId, token_b: AccountId) -> Vec<PoolView> {
+        FEE_TIERS
+            .iter()
+            .filter_map(|fee_bps| self.internal_get_pool_id(&token_a, &token_b, Some(*fee_bps)))
+            .filter_map(|pool_id| self.get_pool(pool_id))
+            .collect()
+    }
+
+    // Accounts with LP share records in the pool and their shares, staked shares are not included
+    pub fn get_liquidity_providers(
+        &self,
+        pool_id: u64,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<(AccountId, U128)> {
+        let pool = self.internal_unwrap_pool(pool_id);
+        let accounts = pool.shares.keys_as_vector();
+        let shares = pool.shares.values_as_vector();
+        (from_index..accounts.len().min(from_index.saturating_add(limit)))
+            .map(|index| {
+                (
+                    accounts.get(index).unwrap(),
+                    U128::from(shares.get(index).unwrap()),
+                )
+            })
+            .collect()
+    }
+
+    // LP shares of the account in the pool
+    pub fn get_shares(&self, pool_id: u64, account_id: AccountId) -> U128 {
+        U128::from(self.internal_unwrap_pool(pool_id).shares_of(&account_id))
+    }
+}
@@ -0,0 +1,39 @@
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near_bindgen, Promise};
+
+use crate::*;
+
+// Token id of attached NEAR in the deposit ledger.
+// Pools initialized with this id trade NEAR as any other token
+pub const NEAR_TOKEN_ID: &str = "near";
+
+pub fn near_token_id() -> AccountId {
+    NEAR_TOKEN_ID.parse().unwrap()
+}
+
+#[near_bindgen]
+impl AMM {
+    // Wraps attached NEAR into the deposit of the predecessor
+    #[payable]
+    pub fn deposit_near(&mut self) -> U128 {
+        let amount = env::attached_deposit();
+        if amount == 0 {
+            fail(AmmError::ZeroDeposit)
+        }
+        let account_id = env::predecessor_account_id();
+        let token_id = near_token_id();
+        self.internal_deposit(&account_id, &token_id, amount);
+        self.internal_add_held(&token_id, amount);
+        log!("Deposit {} NEAR to account {}", amount, account_id);
+        U128::from(self.internal_balance_of(&account_id, &token_id))
+    }
+
+    // Unwraps NEAR from the deposit and sends it back to the predecessor
+    #[payable]
+    pub fn withdraw_near(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        log!("Withdraw {} NEAR from account {}", amount.0, account_id);
+        self.internal_start_transfer(&account_id, &near_token_id(), amount.0)
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use amm::{Action, ActionAmount, ActionResult, AmmError, MIN_LIQUIDITY};
+use near_sdk::json_types::U128;
+use near_sdk::AccountId;
+use near_sdk_sim::{call, view};
+
+use crate::utils::{assert_error, assert_failure, init_with_pool};
+
+#[test]
+fn batch_swaps_and_withdraws_received_tokens() {
+    let initial_balance = 1_000_000_u128;
+    let (_root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+
+    // Swap A to B and send all received B to the wallet
+    let actions = vec![
+        Action::Swap {
+            sell_token_id: ft_a.account_id(),
+            buy_token_id: ft_b.account_id(),
+            sell_amount: ActionAmount::Exact(10_000.into()),
+            min_amount_out: None,
+            referral_id: None,
+            fee_bps: None,
+        },
+        Action::Withdraw {
+            token_id: ft_b.account_id(),
+            amount: ActionAmount::AllReceived,
+        },
+    ];
+    let outcome = call!(
+        alice,
+        amm.execute(actions),
+        deposit = 1,
+        gas = 300000000000000
+    );
+    outcome.assert_success();
+    let results: Vec<ActionResult> = outcome.unwrap_json();
+    let buy_amount = match &results[0] {
+        ActionResult::Swap(result) => result.buy_amount.0,
+        _ => panic!("Swap result expected"),
+    };
+    match &results[1] {
+        ActionResult::Withdraw { amount } => assert_eq!(amount.0, buy_amount),
+        _ => panic!("Withdraw result expected"),
+    }
+    let alice_wallet_b: U128 = view!(ft_b.ft_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(alice_wallet_b.0, buy_amount);
+    let alice_b: U128 = view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_b.0, 50_000);
+
+    // Remove half of liquidity and send received A to the wallet
+    let actions = vec![
+        Action::RemoveLiquidity {
+            token_a_id: ft_a.account_id(),
+            token_b_id: ft_b.account_id(),
+            shares: Some(50_000.into()),
+            fee_bps: None,
+        },
+        Action::Withdraw {
+            token_id: ft_a.account_id(),
+            amount: ActionAmount::AllReceived,
+        },
+    ];
+    let results: Vec<ActionResult> = call!(
+        alice,
+        amm.execute(actions),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .unwrap_json();
+    let removed: HashMap<AccountId, U128> = match &results[0] {
+        ActionResult::RemoveLiquidity { amounts } => amounts.clone(),
+        _ => panic!("Remove liquidity result expected"),
+    };
+    assert_eq!(removed[&ft_a.account_id()].0, 30_000);
+    let alice_wallet_a: U128 = view!(ft_a.ft_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(alice_wallet_a.0, 30_000);
+    let alice_b: U128 = view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_b.0, 50_000 + removed[&ft_b.account_id()].0);
+    let shares: U128 = view!(amm.get_shares(0, alice.account_id())).unwrap_json();
+    assert_eq!(shares.0, 50_000 - MIN_LIQUIDITY);
+}
+
+#[test]
+fn batch_is_atomic() {
+    let initial_balance = 1_000_000_u128;
+    let (_root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+
+    // The second swap sells more than the deposit, so the first one is reverted too
+    let actions = vec![
+        Action::Swap {
+            sell_token_id: ft_a.account_id(),
+            buy_token_id: ft_b.account_id(),
+            sell_amount: ActionAmount::Exact(10_000.into()),
+            min_amount_out: None,
+            referral_id: None,
+            fee_bps: None,
+        },
+        Action::Swap {
+            sell_token_id: ft_a.account_id(),
+            buy_token_id: ft_b.account_id(),
+            sell_amount: ActionAmount::Exact(50_000.into()),
+            min_amount_out: None,
+            referral_id: None,
+            fee_bps: None,
+        },
+    ];
+    let outcome = call!(alice, amm.execute(actions));
+    assert_error(outcome, AmmError::NotEnoughDeposit);
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 50_000);
+    let alice_b: U128 = view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_b.0, 50_000);
+}
+
+#[test]
+fn batch_with_withdrawal_requires_one_yocto() {
+    let initial_balance = 1_000_000_u128;
+    let (_root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+    let withdraw = vec![Action::Withdraw {
+        token_id: ft_a.account_id(),
+        amount: ActionAmount::Exact(1_000.into()),
+    }];
+
+    // Function call access keys can't attach deposit, so they can't withdraw
+    let outcome = call!(alice, amm.execute(withdraw.clone()), gas = 300000000000000);
+    assert_failure(outcome, "Requires attached deposit of exactly 1 yoctoNEAR");
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 50_000);
+
+    // Batches without withdrawals don't need it
+    call!(
+        alice,
+        amm.execute(vec![Action::Swap {
+            sell_token_id: ft_a.account_id(),
+            buy_token_id: ft_b.account_id(),
+            sell_amount: ActionAmount::Exact(1_000.into()),
+            min_amount_out: None,
+            referral_id: None,
+            fee_bps: None,
+        }])
+    )
+    .assert_success();
+    call!(
+        alice,
+        amm.execute(withdraw),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let alice_wallet_a: U128 = view!(ft_a.ft_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(alice_wallet_a.0, 1_000);
+}
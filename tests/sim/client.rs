@@ -0,0 +1,88 @@
+use amm::{PoolView, SwapResult};
+use amm_client::amm_methods::{AddTokensToPoolArgs, GetDepositArgs, GetPoolArgs, SwapArgs};
+use amm_client::ft_methods::{FtBalanceOfArgs, FtTransferCallArgs};
+use amm_client::{AmmClient, FtClient};
+use near_sdk::json_types::U128;
+use near_sdk_sim::{call, view};
+
+use crate::utils::{
+    deposit_tokens, init, register_amm_user, send, send_view, AMM_ID, FT_A_ID, FT_B_ID,
+};
+
+#[test]
+fn typed_client_calls_amm_and_tokens() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init(initial_balance);
+    register_amm_user(&amm, &alice);
+    deposit_tokens(&root, &ft_a, &alice, 100_000);
+    deposit_tokens(&root, &ft_b, &alice, 50_000);
+    let amm_client = AmmClient {
+        contract_id: AMM_ID.parse().unwrap(),
+    };
+    let ft_a_client = FtClient {
+        contract_id: FT_A_ID.parse().unwrap(),
+    };
+
+    send(
+        &alice,
+        amm_client.add_tokens_to_pool(AddTokensToPoolArgs {
+            token_a_name: FT_A_ID.parse().unwrap(),
+            token_a_amount: U128::from(50_000),
+            token_b_name: FT_B_ID.parse().unwrap(),
+            token_b_amount: U128::from(50_000),
+            fee_bps: None,
+        }),
+    )
+    .assert_success();
+    let pool = send_view(&alice, amm_client.get_pool(GetPoolArgs { pool_id: 0 }));
+    let pool: PoolView = pool.unwrap();
+    assert_eq!(pool.reserves, vec![U128::from(50_000), U128::from(50_000)]);
+
+    // Results of calls are parsed to the types of AMM
+    let call = amm_client.swap(SwapArgs {
+        buy_token_name: FT_B_ID.parse().unwrap(),
+        sell_token_name: FT_A_ID.parse().unwrap(),
+        sell_amount: U128::from(10_000),
+        min_amount_out: None,
+        referral_id: None,
+        fee_bps: None,
+    });
+    let outcome = send(&alice, call);
+    outcome.assert_success();
+    let result: SwapResult = outcome.unwrap_json();
+    let alice_b = send_view(
+        &alice,
+        amm_client.get_deposit(GetDepositArgs {
+            account_id: alice.account_id(),
+            token_id: FT_B_ID.parse().unwrap(),
+        }),
+    );
+    assert_eq!(alice_b.0, result.buy_amount.0);
+
+    // Empty message of `ft_transfer_call` deposits tokens
+    call!(
+        root,
+        ft_a.ft_transfer(alice.account_id(), 5_000.into(), None),
+        deposit = 1
+    )
+    .assert_success();
+    send(
+        &alice,
+        ft_a_client.ft_transfer_call(FtTransferCallArgs {
+            receiver_id: AMM_ID.parse().unwrap(),
+            amount: U128::from(5_000),
+            memo: None,
+            msg: "".to_string(),
+        }),
+    )
+    .assert_success();
+    let alice_wallet_a = send_view(
+        &alice,
+        ft_a_client.ft_balance_of(FtBalanceOfArgs {
+            account_id: alice.account_id(),
+        }),
+    );
+    assert_eq!(alice_wallet_a.0, 0);
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 45_000);
+}
@@ -0,0 +1,207 @@
+use amm::{AmmError, FarmView, StakeView, LOCK_BOOSTS, MIN_LIQUIDITY};
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk_sim::{call, to_yocto, view};
+
+use crate::utils::{
+    assert_error, deposit_tokens, init_with_pool, register_amm_user, register_user, AMM_ID,
+    FT_A_ID, FT_B_ID,
+};
+
+#[test]
+fn staked_shares_earn_rewards() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+    let fund_farm = json!({"fund_farm": {"pool_id": 0}}).to_string();
+
+    // Rewards sent to a pool without a farm are refunded
+    let root_a_before: U128 = view!(ft_a.ft_balance_of(root.account_id())).unwrap_json();
+    call!(
+        root,
+        ft_a.ft_transfer_call(
+            AMM_ID.parse().unwrap(),
+            1_000.into(),
+            None,
+            fund_farm.clone()
+        ),
+        deposit = 1
+    );
+    let root_a_after: U128 = view!(ft_a.ft_balance_of(root.account_id())).unwrap_json();
+    assert_eq!(root_a_after.0, root_a_before.0);
+    let farm: Option<FarmView> = view!(amm.get_farm(0)).unwrap_json();
+    assert!(farm.is_none());
+
+    // Only owner sets the farm, its reward token can't be changed later
+    let outcome = call!(
+        alice,
+        amm.set_farm(0, ft_a.account_id(), 10.into()),
+        deposit = 1
+    );
+    assert_error(outcome, AmmError::NotOwner);
+    call!(
+        root,
+        amm.set_farm(0, ft_a.account_id(), 10.into()),
+        deposit = 1
+    )
+    .assert_success();
+    let outcome = call!(
+        root,
+        amm.set_farm(0, ft_b.account_id(), 10.into()),
+        deposit = 1
+    );
+    assert_error(outcome, AmmError::RewardTokenMismatch);
+
+    call!(
+        root,
+        ft_a.ft_transfer_call(AMM_ID.parse().unwrap(), 100_000.into(), None, fund_farm),
+        deposit = 1
+    )
+    .assert_success();
+    let farm: Option<FarmView> = view!(amm.get_farm(0)).unwrap_json();
+    let farm = farm.unwrap();
+    assert_eq!(farm.reward_token_id, ft_a.account_id());
+    assert_eq!(farm.undistributed.0, 100_000);
+
+    // Staked shares leave the pool balance of the account
+    call!(alice, amm.stake_shares(0, 50_000.into(), None)).assert_success();
+    let shares: U128 = view!(amm.get_shares(0, alice.account_id())).unwrap_json();
+    assert_eq!(shares.0, 50_000 - MIN_LIQUIDITY);
+    let staked: U128 = view!(amm.get_staked_shares(0, alice.account_id())).unwrap_json();
+    assert_eq!(staked.0, 50_000);
+
+    // The only stake gets all 10 tokens per second, a block takes a second
+    let pending_before: U128 = view!(amm.get_pending_rewards(0, alice.account_id())).unwrap_json();
+    root.borrow_runtime_mut().produce_blocks(100).unwrap();
+    let pending: U128 = view!(amm.get_pending_rewards(0, alice.account_id())).unwrap_json();
+    assert_eq!(pending.0 - pending_before.0, 10 * 100);
+
+    // Claimed rewards are credited to the deposits
+    let alice_a_before: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    let claimed: U128 = call!(alice, amm.claim_rewards(0)).unwrap_json();
+    let alice_a_after: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a_after.0, alice_a_before.0 + claimed.0);
+
+    // Everything the farm distributed went to the only stake
+    let pending: U128 = view!(amm.get_pending_rewards(0, alice.account_id())).unwrap_json();
+    let farm: Option<FarmView> = view!(amm.get_farm(0)).unwrap_json();
+    assert_eq!(
+        claimed.0 + pending.0,
+        100_000 - farm.unwrap().undistributed.0
+    );
+
+    // Unstaked shares return to the pool balance
+    call!(alice, amm.unstake_shares(0, None)).assert_success();
+    let shares: U128 = view!(amm.get_shares(0, alice.account_id())).unwrap_json();
+    assert_eq!(shares.0, 100_000 - MIN_LIQUIDITY);
+    let staked: U128 = view!(amm.get_staked_shares(0, alice.account_id())).unwrap_json();
+    assert_eq!(staked.0, 0);
+    let farm: Option<FarmView> = view!(amm.get_farm(0)).unwrap_json();
+    let farm = farm.unwrap();
+    assert_eq!(farm.total_staked.0, 0);
+
+    // Rewards aren't spent without stakes, only owner reclaims them to the treasury
+    root.borrow_runtime_mut().produce_blocks(10).unwrap();
+    let undistributed = farm.undistributed.0;
+    let farm: Option<FarmView> = view!(amm.get_farm(0)).unwrap_json();
+    assert_eq!(farm.unwrap().undistributed.0, undistributed);
+    let outcome = call!(alice, amm.reclaim_farm_rewards(0), deposit = 1);
+    assert_error(outcome, AmmError::NotOwner);
+    let root_a_before: U128 = view!(ft_a.ft_balance_of(root.account_id())).unwrap_json();
+    call!(root, amm.reclaim_farm_rewards(0), deposit = 1).assert_success();
+    let root_a_after: U128 = view!(ft_a.ft_balance_of(root.account_id())).unwrap_json();
+    assert_eq!(root_a_after.0, root_a_before.0 + undistributed);
+    let farm: Option<FarmView> = view!(amm.get_farm(0)).unwrap_json();
+    assert_eq!(farm.unwrap().undistributed.0, 0);
+}
+
+#[test]
+fn locked_shares_earn_boosted_rewards() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, _ft_b, amm, alice) = init_with_pool(initial_balance);
+    let fund_farm = json!({"fund_farm": {"pool_id": 0}}).to_string();
+    call!(
+        root,
+        amm.set_farm(0, ft_a.account_id(), 10.into()),
+        deposit = 1
+    )
+    .assert_success();
+    call!(
+        root,
+        ft_a.ft_transfer_call(AMM_ID.parse().unwrap(), 100_000.into(), None, fund_farm),
+        deposit = 1
+    )
+    .assert_success();
+
+    let outcome = call!(alice, amm.stake_shares(0, 10_000.into(), Some(1)));
+    assert_error(outcome, AmmError::LockDurationNotSupported);
+
+    // Shares locked for 30 days get 125% weight
+    let (month, boost) = LOCK_BOOSTS[2];
+    assert_eq!(boost, 125);
+    call!(alice, amm.stake_shares(0, 40_000.into(), Some(month))).assert_success();
+    let stake: Option<StakeView> = view!(amm.get_stake(0, alice.account_id())).unwrap_json();
+    let stake = stake.unwrap();
+    assert_eq!(stake.shares.0, 40_000);
+    assert_eq!(stake.weight.0, 50_000);
+    assert!(stake.lock_until > 0);
+    let farm: Option<FarmView> = view!(amm.get_farm(0)).unwrap_json();
+    let farm = farm.unwrap();
+    assert_eq!(farm.total_staked.0, 40_000);
+    assert_eq!(farm.total_weight.0, 50_000);
+
+    // The lock can't be removed or shortened, more shares are locked with the whole stake
+    let outcome = call!(alice, amm.stake_shares(0, 10_000.into(), None));
+    assert_error(outcome, AmmError::LockShortened);
+    let (week, _) = LOCK_BOOSTS[1];
+    let outcome = call!(alice, amm.stake_shares(0, 10_000.into(), Some(week)));
+    assert_error(outcome, AmmError::LockShortened);
+    let (quarter, _) = LOCK_BOOSTS[3];
+    call!(alice, amm.stake_shares(0, 20_000.into(), Some(quarter))).assert_success();
+    let stake: Option<StakeView> = view!(amm.get_stake(0, alice.account_id())).unwrap_json();
+    let stake = stake.unwrap();
+    assert_eq!(stake.shares.0, 60_000);
+    assert_eq!(stake.weight.0, 90_000);
+    let farm: Option<FarmView> = view!(amm.get_farm(0)).unwrap_json();
+    assert_eq!(farm.unwrap().total_weight.0, 90_000);
+
+    // Bob stakes 10_000 shares without a lock, rewards are split by weights
+    // 90_000 and 10_000, so the boost gives Alice 90% instead of 85.7% by shares
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    register_user(FT_A_ID, &bob);
+    register_user(FT_B_ID, &bob);
+    register_amm_user(&amm, &bob);
+    deposit_tokens(&root, &ft_a, &bob, 5_000);
+    deposit_tokens(&root, &ft_b, &bob, 5_000);
+    call!(
+        bob,
+        amm.add_tokens_to_pool(
+            ft_a.account_id(),
+            5_000.into(),
+            ft_b.account_id(),
+            5_000.into(),
+            None
+        )
+    )
+    .assert_success();
+    call!(bob, amm.stake_shares(0, 10_000.into(), None)).assert_success();
+    let farm: Option<FarmView> = view!(amm.get_farm(0)).unwrap_json();
+    assert_eq!(farm.unwrap().total_weight.0, 100_000);
+    let alice_before: U128 = view!(amm.get_pending_rewards(0, alice.account_id())).unwrap_json();
+    let bob_before: U128 = view!(amm.get_pending_rewards(0, bob.account_id())).unwrap_json();
+    root.borrow_runtime_mut().produce_blocks(10).unwrap();
+    let alice_pending: U128 = view!(amm.get_pending_rewards(0, alice.account_id())).unwrap_json();
+    let bob_pending: U128 = view!(amm.get_pending_rewards(0, bob.account_id())).unwrap_json();
+    assert_eq!(alice_pending.0 - alice_before.0, 10 * 10 * 90_000 / 100_000);
+    assert_eq!(bob_pending.0 - bob_before.0, 10 * 10 * 10_000 / 100_000);
+
+    // Locked shares can't be unstaked, but rewards are claimed
+    let outcome = call!(alice, amm.unstake_shares(0, None));
+    assert_error(outcome, AmmError::SharesLocked);
+    let claimed: U128 = call!(alice, amm.claim_rewards(0)).unwrap_json();
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 50_000 + claimed.0);
+    // The claim adds 9 tokens per second passed since the view
+    assert_eq!((claimed.0 - alice_pending.0) % 9, 0);
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use crate::utils::{
+    assert_error, deposit_tokens, init, init_with_pool, pool_reserve, register_amm_user,
+};
+use amm::{AmmError, DynamicFeeConfig, PoolView, SwapResult};
+use near_sdk::json_types::U128;
+use near_sdk::AccountId;
+use near_sdk_sim::{call, to_yocto, view};
+
+#[test]
+fn protocol_fees_are_claimed_to_treasury() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+
+    // Only owner sets the protocol fee
+    let outcome = call!(alice, amm.set_protocol_fee(0, 5_000), deposit = 1);
+    assert_error(outcome, AmmError::NotOwner);
+    call!(root, amm.set_protocol_fee(0, 5_000), deposit = 1).assert_success();
+
+    // Half of 0.3% fee is accrued for the protocol, the rest stays in the pool
+    let pool_a_before: U128 = pool_reserve(&amm, 0, &ft_a.account_id());
+    call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            None,
+            None
+        )
+    )
+    .assert_success();
+    let pool_a_after: U128 = pool_reserve(&amm, 0, &ft_a.account_id());
+    assert_eq!(pool_a_after.0, pool_a_before.0 + 10_000 - 15);
+    let protocol_fees: HashMap<AccountId, U128> = view!(amm.get_protocol_fees(0)).unwrap_json();
+    assert_eq!(protocol_fees[&ft_a.account_id()].0, 15);
+    assert_eq!(protocol_fees[&ft_b.account_id()].0, 0);
+
+    // Fees are sent to the treasury, which is the owner by default
+    let treasury: AccountId = view!(amm.get_treasury()).unwrap_json();
+    assert_eq!(treasury, root.account_id());
+    let root_balance_before: U128 = view!(ft_a.ft_balance_of(root.account_id())).unwrap_json();
+    let held_before: U128 = view!(amm.get_held_balance(ft_a.account_id())).unwrap_json();
+    call!(
+        root,
+        amm.claim_protocol_fees(0),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let root_balance_after: U128 = view!(ft_a.ft_balance_of(root.account_id())).unwrap_json();
+    assert_eq!(root_balance_after.0, root_balance_before.0 + 15);
+    let protocol_fees: HashMap<AccountId, U128> = view!(amm.get_protocol_fees(0)).unwrap_json();
+    assert_eq!(protocol_fees[&ft_a.account_id()].0, 0);
+    let held: U128 = view!(amm.get_held_balance(ft_a.account_id())).unwrap_json();
+    assert_eq!(held.0, held_before.0 - 15);
+
+    // Fees return to the pool and stay held if the treasury can't receive them
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    call!(root, amm.set_treasury(bob.account_id()), deposit = 1).assert_success();
+    call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            None,
+            None
+        )
+    )
+    .assert_success();
+    let protocol_fees: HashMap<AccountId, U128> = view!(amm.get_protocol_fees(0)).unwrap_json();
+    let fee = protocol_fees[&ft_a.account_id()].0;
+    assert!(fee > 0);
+    let held_before: U128 = view!(amm.get_held_balance(ft_a.account_id())).unwrap_json();
+    call!(
+        root,
+        amm.claim_protocol_fees(0),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let protocol_fees: HashMap<AccountId, U128> = view!(amm.get_protocol_fees(0)).unwrap_json();
+    assert_eq!(protocol_fees[&ft_a.account_id()].0, fee);
+    let held: U128 = view!(amm.get_held_balance(ft_a.account_id())).unwrap_json();
+    assert_eq!(held.0, held_before.0);
+}
+
+#[test]
+fn referral_gets_part_of_swap_fee() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+
+    // Referrer must be registered in AMM
+    let outcome = call!(root, amm.set_referral(bob.account_id(), 2_000), deposit = 1);
+    assert_error(outcome, AmmError::ReferrerNotRegistered);
+    register_amm_user(&amm, &bob);
+    call!(root, amm.set_referral(bob.account_id(), 2_000), deposit = 1).assert_success();
+    let referral_fee: Option<u32> = view!(amm.get_referral_fee(bob.account_id())).unwrap_json();
+    assert_eq!(referral_fee, Some(2_000));
+
+    // 20% of 0.3% fee goes to the referrer
+    let outcome = call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            Some(bob.account_id()),
+            None
+        )
+    );
+    outcome.assert_success();
+    let bob_deposit: U128 =
+        view!(amm.get_deposit(bob.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(bob_deposit.0, 6);
+    let pool_a: U128 = pool_reserve(&amm, 0, &ft_a.account_id());
+    assert_eq!(pool_a.0, 50_000 + 10_000 - 6);
+
+    // Referral is included to the swap event
+    let event = outcome
+        .logs()
+        .iter()
+        .find(|log| log.starts_with("EVENT_JSON:"))
+        .unwrap()
+        .clone();
+    assert!(event.contains("\"event\":\"swap\""));
+    assert!(event.contains("\"referral_id\":\"bob\""));
+    assert!(event.contains("\"referral_fee\":\"6\""));
+
+    // Unknown referrer gets nothing
+    call!(root, amm.remove_referral(bob.account_id()), deposit = 1).assert_success();
+    call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            Some(bob.account_id()),
+            None
+        )
+    )
+    .assert_success();
+    let bob_deposit: U128 =
+        view!(amm.get_deposit(bob.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(bob_deposit.0, 6);
+}
+
+#[test]
+fn dynamic_fee_follows_volatility() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init(initial_balance);
+    register_amm_user(&amm, &alice);
+    deposit_tokens(&root, &ft_a, &alice, 200_000);
+    deposit_tokens(&root, &ft_b, &alice, 100_000);
+    call!(
+        alice,
+        amm.add_tokens_to_pool(
+            ft_a.account_id(),
+            50_000.into(),
+            ft_b.account_id(),
+            50_000.into(),
+            None
+        )
+    )
+    .assert_success();
+
+    let config = DynamicFeeConfig {
+        min_fee_bps: 5,
+        max_fee_bps: 100,
+        max_volatility_bps: 1_000,
+        decay_period_sec: 3_600,
+    };
+    let outcome = call!(
+        alice,
+        amm.set_dynamic_fee(0, Some(config.clone())),
+        deposit = 1
+    );
+    assert_error(outcome, AmmError::NotOwner);
+    let invalid_config = DynamicFeeConfig {
+        min_fee_bps: 200,
+        ..config.clone()
+    };
+    let outcome = call!(
+        root,
+        amm.set_dynamic_fee(0, Some(invalid_config)),
+        deposit = 1
+    );
+    assert_error(outcome, AmmError::InvalidDynamicFee);
+    call!(
+        root,
+        amm.set_dynamic_fee(0, Some(config.clone())),
+        deposit = 1
+    )
+    .assert_success();
+
+    // Calm pool charges the min fee
+    let pool: PoolView = view!(amm.get_pool(0)).unwrap_json();
+    assert_eq!(pool.dynamic_fee, Some(config));
+    assert_eq!(pool.current_fee_bps, 5);
+    let outcome = call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            50_000.into(),
+            None,
+            None,
+            None
+        )
+    );
+    outcome.assert_success();
+    let result: SwapResult = outcome.unwrap_json();
+    assert_eq!(result.fee_bps, 5);
+    assert_eq!(result.fee.0, 25);
+    let event = outcome
+        .logs()
+        .iter()
+        .find(|log| log.starts_with("EVENT_JSON:"))
+        .unwrap()
+        .clone();
+    assert!(event.contains("\"fee_bps\":5"));
+
+    // Price has moved a lot, so the next swap is charged with the max fee
+    let pool: PoolView = view!(amm.get_pool(0)).unwrap_json();
+    assert_eq!(pool.current_fee_bps, 100);
+    let result: SwapResult = call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            None,
+            None
+        )
+    )
+    .unwrap_json();
+    assert_eq!(result.fee_bps, 100);
+    assert_eq!(result.fee.0, 100);
+
+    // Static fee tier is back without the dynamic fee
+    call!(root, amm.set_dynamic_fee(0, None), deposit = 1).assert_success();
+    let pool: PoolView = view!(amm.get_pool(0)).unwrap_json();
+    assert_eq!(pool.current_fee_bps, 30);
+}
@@ -0,0 +1,189 @@
+use amm::{AdminAction, AmmError, GovernanceConfig, PoolView, ProposalStatus, ProposalView};
+use near_sdk::json_types::U128;
+use near_sdk_sim::{call, to_yocto, view};
+
+use crate::utils::{
+    assert_error, deposit_tokens, init, register_amm_user, register_user, FT_A_ID, FT_B_ID,
+};
+
+#[test]
+fn proposal_is_executed_after_voting_and_timelock() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init(initial_balance);
+    register_amm_user(&amm, &alice);
+    deposit_tokens(&root, &ft_b, &alice, 100_000);
+    call!(
+        root,
+        amm.enable_governance(GovernanceConfig {
+            token_id: ft_b.account_id(),
+            proposal_threshold: 1_000.into(),
+            voting_period_sec: 10,
+            quorum_bps: 5_000,
+            timelock_sec: 10,
+        }),
+        deposit = 1
+    )
+    .assert_success();
+
+    // Owner methods are available only through proposals
+    let outcome = call!(
+        root,
+        amm.add_pool(ft_a.account_id(), ft_b.account_id(), 5),
+        deposit = 1
+    );
+    assert_error(outcome, AmmError::OwnerDisabledByGovernance);
+
+    let action = AdminAction::AddPool {
+        token_a: ft_a.account_id(),
+        token_b: ft_b.account_id(),
+        fee_bps: 5,
+    };
+    let outcome = call!(
+        alice,
+        amm.create_proposal("Add 0.05% pool".to_string(), action.clone()),
+        deposit = to_yocto("0.1")
+    );
+    assert_error(outcome, AmmError::NotEnoughVotingPowerToPropose);
+
+    call!(alice, amm.lock_voting_tokens(10_000.into())).assert_success();
+    let power: U128 = view!(amm.get_voting_power(alice.account_id())).unwrap_json();
+    assert_eq!(power.0, 10_000);
+    let alice_b: U128 = view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_b.0, 90_000);
+    root.borrow_runtime_mut().produce_blocks(1).unwrap();
+
+    let proposal_id: u64 = call!(
+        alice,
+        amm.create_proposal("Add 0.05% pool".to_string(), action),
+        deposit = to_yocto("0.1")
+    )
+    .unwrap_json();
+    call!(
+        alice,
+        amm.vote(proposal_id, true),
+        deposit = to_yocto("0.1")
+    )
+    .assert_success();
+    let outcome = call!(
+        alice,
+        amm.vote(proposal_id, true),
+        deposit = to_yocto("0.1")
+    );
+    assert_error(outcome, AmmError::AlreadyVoted);
+
+    // Tokens locked after the snapshot don't give votes
+    call!(alice, amm.unlock_voting_tokens(10_000.into())).assert_success();
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    register_user(FT_B_ID, &bob);
+    register_amm_user(&amm, &bob);
+    deposit_tokens(&root, &ft_b, &bob, 50_000);
+    call!(bob, amm.lock_voting_tokens(50_000.into())).assert_success();
+    let outcome = call!(bob, amm.vote(proposal_id, false), deposit = to_yocto("0.1"));
+    assert_error(outcome, AmmError::NoVotingPowerAtSnapshot);
+
+    let outcome = call!(root, amm.execute_proposal(proposal_id));
+    assert_error(outcome, AmmError::VotingIsNotOver);
+    root.borrow_runtime_mut().produce_blocks(12).unwrap();
+    let outcome = call!(root, amm.execute_proposal(proposal_id));
+    assert_error(outcome, AmmError::ProposalInTimelock);
+    root.borrow_runtime_mut().produce_blocks(12).unwrap();
+
+    call!(root, amm.execute_proposal(proposal_id)).assert_success();
+    let proposal: Option<ProposalView> = view!(amm.get_proposal(proposal_id)).unwrap_json();
+    let proposal = proposal.unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Executed);
+    assert_eq!(proposal.votes_for.0, 10_000);
+    let pools: Vec<PoolView> =
+        view!(amm.get_pair_pools(ft_a.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(pools.len(), 2);
+    let outcome = call!(root, amm.execute_proposal(proposal_id));
+    assert_error(outcome, AmmError::ProposalExecuted);
+}
+
+#[test]
+fn locked_tokens_are_returned_after_governance_token_change() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init(initial_balance);
+    register_amm_user(&amm, &alice);
+    deposit_tokens(&root, &ft_a, &alice, 100_000);
+    deposit_tokens(&root, &ft_b, &alice, 100_000);
+    let config = GovernanceConfig {
+        token_id: ft_b.account_id(),
+        proposal_threshold: 1_000.into(),
+        voting_period_sec: 10,
+        quorum_bps: 5_000,
+        timelock_sec: 0,
+    };
+    call!(root, amm.enable_governance(config.clone()), deposit = 1).assert_success();
+    call!(alice, amm.lock_voting_tokens(10_000.into())).assert_success();
+    root.borrow_runtime_mut().produce_blocks(1).unwrap();
+
+    let action = AdminAction::SetGovernanceConfig {
+        config: GovernanceConfig {
+            token_id: ft_a.account_id(),
+            ..config
+        },
+    };
+    let proposal_id: u64 = call!(
+        alice,
+        amm.create_proposal("Govern by token A".to_string(), action),
+        deposit = to_yocto("0.1")
+    )
+    .unwrap_json();
+    call!(
+        alice,
+        amm.vote(proposal_id, true),
+        deposit = to_yocto("0.1")
+    )
+    .assert_success();
+    root.borrow_runtime_mut().produce_blocks(12).unwrap();
+    call!(root, amm.execute_proposal(proposal_id)).assert_success();
+
+    // Locked token B doesn't vote or propose anymore, only token A counts
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    register_user(FT_A_ID, &bob);
+    register_amm_user(&amm, &bob);
+    deposit_tokens(&root, &ft_a, &bob, 5_000);
+    call!(bob, amm.lock_voting_tokens(5_000.into())).assert_success();
+    let total: U128 = view!(amm.get_total_voting_power()).unwrap_json();
+    assert_eq!(total.0, 5_000);
+    root.borrow_runtime_mut().produce_blocks(1).unwrap();
+    let outcome = call!(
+        alice,
+        amm.create_proposal(
+            "Claim fees".to_string(),
+            AdminAction::ClaimProtocolFees { pool_id: 0 }
+        ),
+        deposit = to_yocto("0.1")
+    );
+    assert_error(outcome, AmmError::NotEnoughVotingPowerToPropose);
+    let proposal_id: u64 = call!(
+        bob,
+        amm.create_proposal(
+            "Claim fees".to_string(),
+            AdminAction::ClaimProtocolFees { pool_id: 0 }
+        ),
+        deposit = to_yocto("0.1")
+    )
+    .unwrap_json();
+    let proposal: Option<ProposalView> = view!(amm.get_proposal(proposal_id)).unwrap_json();
+    assert_eq!(proposal.unwrap().total_voting_power.0, 5_000);
+    let outcome = call!(
+        alice,
+        amm.vote(proposal_id, true),
+        deposit = to_yocto("0.1")
+    );
+    assert_error(outcome, AmmError::NoVotingPowerAtSnapshot);
+
+    // Token B stays locked until it's unlocked, then token A can be locked
+    let outcome = call!(alice, amm.lock_voting_tokens(10_000.into()));
+    assert_error(outcome, AmmError::VotingTokenChanged);
+    call!(alice, amm.unlock_voting_tokens(10_000.into())).assert_success();
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    let alice_b: U128 = view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 100_000);
+    assert_eq!(alice_b.0, 100_000);
+    call!(alice, amm.lock_voting_tokens(10_000.into())).assert_success();
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 90_000);
+}
@@ -0,0 +1,194 @@
+use amm::{AmmError, LimitOrderView, PRICE_PRECISION};
+use near_sdk::json_types::U128;
+use near_sdk_sim::{call, to_yocto, view};
+
+use crate::utils::{
+    assert_error, deposit_tokens, init, init_with_pool, register_amm_user, register_user, FT_A_ID,
+};
+
+#[test]
+fn limit_order_is_filled_by_swap_crossing_the_price() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init(initial_balance);
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    register_user(FT_A_ID, &bob);
+    register_amm_user(&amm, &alice);
+    register_amm_user(&amm, &bob);
+    deposit_tokens(&root, &ft_a, &alice, 200_000);
+    deposit_tokens(&root, &ft_b, &alice, 100_000);
+    deposit_tokens(&root, &ft_a, &bob, 20_000);
+    call!(
+        alice,
+        amm.add_tokens_to_pool(
+            ft_a.account_id(),
+            50_000.into(),
+            ft_b.account_id(),
+            50_000.into(),
+            None
+        )
+    )
+    .assert_success();
+
+    // Sell 10_000 B for at least 1.1 A each, the pool gives 1 A now
+    let outcome = call!(
+        alice,
+        amm.place_limit_order(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            (PRICE_PRECISION * 11 / 10).into(),
+            None
+        )
+    );
+    outcome.assert_success();
+    let order_id: u64 = outcome.unwrap_json();
+    let orders: Vec<LimitOrderView> =
+        view!(amm.get_account_limit_orders(alice.account_id(), 0, 10)).unwrap_json();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].order_id, order_id);
+    assert_eq!(orders[0].amount.0, 10_000);
+    let orders: Vec<LimitOrderView> = view!(amm.get_pool_limit_orders(0, 0, 10)).unwrap_json();
+    assert_eq!(orders.len(), 1);
+    let alice_b: U128 = view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_b.0, 40_000);
+
+    // Only maker cancels the order
+    let outcome = call!(bob, amm.cancel_limit_order(order_id));
+    assert_error(outcome, AmmError::NotLimitOrderMaker);
+
+    // Bob buys B and makes it more expensive than the limit, so the order is filled
+    let alice_a_before: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    let outcome = call!(
+        bob,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            20_000.into(),
+            None,
+            None,
+            None
+        )
+    );
+    outcome.assert_success();
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"limit_order_fill\"")));
+    let alice_a_after: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a_after.0 - alice_a_before.0, 15_266);
+    let order: Option<LimitOrderView> = view!(amm.get_limit_order(order_id)).unwrap_json();
+    assert!(order.is_none());
+    let orders: Vec<LimitOrderView> =
+        view!(amm.get_account_limit_orders(alice.account_id(), 0, 10)).unwrap_json();
+    assert!(orders.is_empty());
+}
+
+#[test]
+fn limit_order_is_cancelled() {
+    let initial_balance = 1_000_000_u128;
+    let (_root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+
+    let order_id: u64 = call!(
+        alice,
+        amm.place_limit_order(
+            ft_a.account_id(),
+            ft_b.account_id(),
+            30_000.into(),
+            (PRICE_PRECISION * 2).into(),
+            None
+        )
+    )
+    .unwrap_json();
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 20_000);
+
+    // Account with open orders can't be unregistered
+    let outcome = call!(alice, amm.storage_unregister(Some(true)), deposit = 1);
+    assert_error(outcome, AmmError::UnregisterWithLiquidity);
+
+    let outcome = call!(alice, amm.cancel_limit_order(order_id));
+    outcome.assert_success();
+    let returned: U128 = outcome.unwrap_json();
+    assert_eq!(returned.0, 30_000);
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 50_000);
+    let orders: Vec<LimitOrderView> = view!(amm.get_pool_limit_orders(0, 0, 10)).unwrap_json();
+    assert!(orders.is_empty());
+}
+
+#[test]
+fn dust_limit_order_does_not_block_the_book() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    register_user(FT_A_ID, &bob);
+    register_amm_user(&amm, &bob);
+    deposit_tokens(&root, &ft_a, &bob, 20_000);
+
+    // B gets cheaper than 0.35 A, so orders below are placed without fills
+    call!(
+        alice,
+        amm.swap(
+            ft_a.account_id(),
+            ft_b.account_id(),
+            40_000.into(),
+            None,
+            None,
+            None
+        )
+    )
+    .assert_success();
+    let place_order = |amount: u128, limit_price: u128| -> u64 {
+        call!(
+            alice,
+            amm.place_limit_order(
+                ft_b.account_id(),
+                ft_a.account_id(),
+                amount.into(),
+                limit_price.into(),
+                None
+            )
+        )
+        .unwrap_json()
+    };
+
+    // 1 B buys nothing at any price below 1 A, the order is ahead by the lower limit
+    let dust_order_id = place_order(1, PRICE_PRECISION * 35 / 100);
+    let order_id = place_order(1_000, PRICE_PRECISION * 40 / 100);
+    let orders: Vec<LimitOrderView> = view!(amm.get_pool_limit_orders(0, 0, 10)).unwrap_json();
+    assert_eq!(orders.len(), 2);
+    assert_eq!(orders[0].order_id, dust_order_id);
+    let orders: Vec<LimitOrderView> = view!(amm.get_pool_limit_orders(0, 1, 10)).unwrap_json();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].order_id, order_id);
+    let alice_b: U128 = view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_b.0, 8_999);
+
+    // Bob makes B more expensive than both limits, the dust order is closed with a refund
+    // and the order behind it is filled
+    let alice_a_before: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    call!(
+        bob,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            20_000.into(),
+            None,
+            None,
+            None
+        )
+    )
+    .assert_success();
+    let order: Option<LimitOrderView> = view!(amm.get_limit_order(dust_order_id)).unwrap_json();
+    assert!(order.is_none());
+    let order: Option<LimitOrderView> = view!(amm.get_limit_order(order_id)).unwrap_json();
+    assert!(order.is_none());
+    let alice_b: U128 = view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_b.0, 9_000);
+    let alice_a_after: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert!(alice_a_after.0 - alice_a_before.0 >= 400);
+}
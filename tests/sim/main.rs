@@ -1,2 +1,20 @@
+mod actions;
+mod client;
+mod farming;
+mod fees;
+mod governance;
+mod limit_orders;
+mod multisig;
+mod pools;
+mod positions;
+mod reserves;
+mod slippage;
+mod stats;
+mod storage;
+mod swap_and_call;
 mod test;
+mod timelock;
+mod transfers;
+mod twamm;
 mod utils;
+mod wnear;
@@ -0,0 +1,161 @@
+use amm::{
+    AdminAction, AmmError, MultisigConfig, MultisigRequestView, QueuedActionView, TimelockConfig,
+};
+use near_sdk::AccountId;
+use near_sdk_sim::{call, to_yocto, view};
+
+use crate::utils::{assert_error, init};
+
+#[test]
+fn multisig_request_is_executed_by_threshold() {
+    let initial_balance = 1_000_000_u128;
+    let (root, _ft_a, _ft_b, amm, alice) = init(initial_balance);
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    let carol = root.create_user("carol".parse().unwrap(), to_yocto("100"));
+    call!(
+        root,
+        amm.set_multisig(MultisigConfig {
+            members: vec![alice.account_id(), bob.account_id(), carol.account_id()],
+            threshold: 2,
+            request_ttl_sec: 100,
+        }),
+        deposit = 1
+    )
+    .assert_success();
+
+    let outcome = call!(root, amm.set_treasury(bob.account_id()), deposit = 1);
+    assert_error(outcome, AmmError::MultisigRequired);
+    let action = AdminAction::SetTreasury {
+        treasury_id: bob.account_id(),
+    };
+    let outcome = call!(
+        root,
+        amm.create_multisig_request(action.clone()),
+        deposit = to_yocto("0.1")
+    );
+    assert_error(outcome, AmmError::NotMultisigMember);
+
+    // The creator confirms the request, the second confirmation executes it
+    let request_id: u64 = call!(
+        alice,
+        amm.create_multisig_request(action),
+        deposit = to_yocto("0.1")
+    )
+    .unwrap_json();
+    let request: Option<MultisigRequestView> =
+        view!(amm.get_multisig_request(request_id)).unwrap_json();
+    assert_eq!(request.unwrap().confirmations, vec![alice.account_id()]);
+    let outcome = call!(alice, amm.confirm_multisig_request(request_id), deposit = 1);
+    assert_error(outcome, AmmError::AlreadyConfirmed);
+
+    call!(bob, amm.confirm_multisig_request(request_id), deposit = 1).assert_success();
+    let treasury: AccountId = view!(amm.get_treasury()).unwrap_json();
+    assert_eq!(treasury, bob.account_id());
+    let requests: Vec<MultisigRequestView> = view!(amm.get_multisig_requests(0, 10)).unwrap_json();
+    assert!(requests.is_empty());
+}
+
+#[test]
+fn multisig_request_expires() {
+    let initial_balance = 1_000_000_u128;
+    let (root, _ft_a, _ft_b, amm, alice) = init(initial_balance);
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    call!(
+        root,
+        amm.set_multisig(MultisigConfig {
+            members: vec![alice.account_id(), bob.account_id()],
+            threshold: 2,
+            request_ttl_sec: 10,
+        }),
+        deposit = 1
+    )
+    .assert_success();
+    let request_id: u64 = call!(
+        alice,
+        amm.create_multisig_request(AdminAction::SetOwner {
+            owner_id: alice.account_id(),
+        }),
+        deposit = to_yocto("0.1")
+    )
+    .unwrap_json();
+    let outcome = call!(root, amm.remove_expired_multisig_request(request_id));
+    assert_error(outcome, AmmError::RequestNotExpired);
+
+    root.borrow_runtime_mut().produce_blocks(12).unwrap();
+    let outcome = call!(bob, amm.confirm_multisig_request(request_id), deposit = 1);
+    assert_error(outcome, AmmError::RequestExpired);
+    call!(root, amm.remove_expired_multisig_request(request_id)).assert_success();
+    let request: Option<MultisigRequestView> =
+        view!(amm.get_multisig_request(request_id)).unwrap_json();
+    assert!(request.is_none());
+    let owner: AccountId = view!(amm.get_owner()).unwrap_json();
+    assert_eq!(owner, root.account_id());
+}
+
+#[test]
+fn multisig_request_is_queued_with_timelock() {
+    let initial_balance = 1_000_000_u128;
+    let (root, _ft_a, _ft_b, amm, alice) = init(initial_balance);
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    call!(
+        root,
+        amm.set_timelock(TimelockConfig {
+            delay_sec: 10,
+            guardians: vec![],
+        }),
+        deposit = 1
+    )
+    .assert_success();
+    let owner_action_id: u64 = call!(
+        root,
+        amm.queue_action(AdminAction::SetTreasury {
+            treasury_id: root.account_id(),
+        }),
+        deposit = to_yocto("0.1")
+    )
+    .unwrap_json();
+    let multisig_action_id: u64 = call!(
+        root,
+        amm.queue_action(AdminAction::SetMultisig {
+            config: MultisigConfig {
+                members: vec![alice.account_id(), bob.account_id()],
+                threshold: 2,
+                request_ttl_sec: 100,
+            },
+        }),
+        deposit = to_yocto("0.1")
+    )
+    .unwrap_json();
+    root.borrow_runtime_mut().produce_blocks(12).unwrap();
+    call!(root, amm.execute_action(multisig_action_id)).assert_success();
+
+    // Actions queued by the owner need the multisig now
+    let outcome = call!(root, amm.execute_action(owner_action_id));
+    assert_error(outcome, AmmError::MultisigRequired);
+
+    // The confirmed request waits for the delay like owner actions
+    let treasury_before: AccountId = view!(amm.get_treasury()).unwrap_json();
+    let request_id: u64 = call!(
+        alice,
+        amm.create_multisig_request(AdminAction::SetTreasury {
+            treasury_id: bob.account_id(),
+        }),
+        deposit = to_yocto("0.1")
+    )
+    .unwrap_json();
+    call!(bob, amm.confirm_multisig_request(request_id), deposit = 1).assert_success();
+    let treasury: AccountId = view!(amm.get_treasury()).unwrap_json();
+    assert_eq!(treasury, treasury_before);
+    let actions: Vec<QueuedActionView> = view!(amm.get_queued_actions(0, 10)).unwrap_json();
+    let action_id = actions
+        .iter()
+        .find(|action| action.action_id != owner_action_id)
+        .unwrap()
+        .action_id;
+    let outcome = call!(root, amm.execute_action(action_id));
+    assert_error(outcome, AmmError::ActionNotReady);
+    root.borrow_runtime_mut().produce_blocks(12).unwrap();
+    call!(root, amm.execute_action(action_id)).assert_success();
+    let treasury: AccountId = view!(amm.get_treasury()).unwrap_json();
+    assert_eq!(treasury, bob.account_id());
+}
@@ -0,0 +1,253 @@
+use amm::{AmmError, PoolInfo, PoolView, SwapResult, MIN_LIQUIDITY, PRICE_PRECISION};
+use near_sdk::json_types::U128;
+use near_sdk::AccountId;
+use near_sdk_sim::{call, to_yocto, view};
+
+use crate::utils::{
+    assert_error, deposit_tokens, init, init_with_pool, register_amm_user, register_user, FT_A_ID,
+};
+
+#[test]
+fn pools_of_pair_with_different_fee_tiers() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init(initial_balance);
+    register_amm_user(&amm, &alice);
+    deposit_tokens(&root, &ft_a, &alice, 200_000);
+    deposit_tokens(&root, &ft_b, &alice, 200_000);
+
+    // Only owner adds pools and only with allowed fee tiers
+    let outcome = call!(
+        alice,
+        amm.add_pool(ft_a.account_id(), ft_b.account_id(), 100),
+        deposit = 1
+    );
+    assert_error(outcome, AmmError::NotOwner);
+    let outcome = call!(
+        root,
+        amm.add_pool(ft_a.account_id(), ft_b.account_id(), 50),
+        deposit = 1
+    );
+    assert_error(outcome, AmmError::FeeTierNotSupported);
+    let outcome = call!(
+        root,
+        amm.add_pool(ft_b.account_id(), ft_a.account_id(), 30),
+        deposit = 1
+    );
+    assert_error(outcome, AmmError::PoolAlreadyExists);
+    let outcome = call!(
+        root,
+        amm.add_pool(ft_b.account_id(), ft_a.account_id(), 100),
+        deposit = 1
+    );
+    outcome.assert_success();
+    let pool_id: u64 = outcome.unwrap_json();
+    assert_eq!(pool_id, 1);
+    let found_pool_id: Option<u64> =
+        view!(amm.get_pool_id(ft_a.account_id(), ft_b.account_id(), Some(100))).unwrap_json();
+    assert_eq!(found_pool_id, Some(1));
+
+    // Liquidity is added to every tier separately
+    for fee_bps in [30, 100] {
+        call!(
+            alice,
+            amm.add_tokens_to_pool(
+                ft_a.account_id(),
+                50_000.into(),
+                ft_b.account_id(),
+                50_000.into(),
+                Some(fee_bps)
+            )
+        )
+        .assert_success();
+    }
+    let pools: Vec<PoolView> =
+        view!(amm.get_pair_pools(ft_a.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(pools.len(), 2);
+    assert_eq!(pools[0].fee_bps, 30);
+    assert_eq!(pools[1].fee_bps, 100);
+    assert_eq!(
+        pools[1].token_ids,
+        vec![ft_b.account_id(), ft_a.account_id()]
+    );
+    assert_eq!(pools[1].reserves, vec![U128(50_000), U128(50_000)]);
+    let shares: U128 = view!(amm.get_shares(1, alice.account_id())).unwrap_json();
+    assert_eq!(shares.0, 100_000 - MIN_LIQUIDITY);
+
+    // Swap with the higher fee tier gives less tokens
+    let low_fee: SwapResult = call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            None,
+            None
+        )
+    )
+    .unwrap_json();
+    let high_fee: SwapResult = call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            None,
+            Some(100)
+        )
+    )
+    .unwrap_json();
+    assert_eq!(low_fee.fee_bps, 30);
+    assert_eq!(high_fee.fee_bps, 100);
+    assert!(high_fee.buy_amount.0 < low_fee.buy_amount.0);
+    let pool: PoolView = view!(amm.get_pool(1)).unwrap_json();
+    assert_eq!(pool.reserves[1].0, 60_000);
+    assert_eq!(pool.reserves[0].0, 50_000 - high_fee.buy_amount.0);
+
+    // Pool of missing tier is not found
+    let outcome = call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            None,
+            Some(5)
+        )
+    );
+    assert_error(outcome, AmmError::PoolNotFound);
+}
+
+#[test]
+fn pool_is_moved_to_another_fee_tier() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init(initial_balance);
+    call!(
+        root,
+        amm.add_pool(ft_a.account_id(), ft_b.account_id(), 100),
+        deposit = 1
+    )
+    .assert_success();
+
+    // Only owner changes the fee and only to a free allowed tier of the pair
+    let outcome = call!(alice, amm.set_fee(0, 5), deposit = 1);
+    assert_error(outcome, AmmError::NotOwner);
+    let outcome = call!(root, amm.set_fee(0, 50), deposit = 1);
+    assert_error(outcome, AmmError::FeeTierNotSupported);
+    let outcome = call!(root, amm.set_fee(0, 100), deposit = 1);
+    assert_error(outcome, AmmError::PoolAlreadyExists);
+    call!(root, amm.set_fee(0, 5), deposit = 1).assert_success();
+
+    let pool: Option<PoolView> = view!(amm.get_pool(0)).unwrap_json();
+    assert_eq!(pool.unwrap().fee_bps, 5);
+    let found_pool_id: Option<u64> =
+        view!(amm.get_pool_id(ft_a.account_id(), ft_b.account_id(), Some(5))).unwrap_json();
+    assert_eq!(found_pool_id, Some(0));
+    let found_pool_id: Option<u64> =
+        view!(amm.get_pool_id(ft_a.account_id(), ft_b.account_id(), Some(30))).unwrap_json();
+    assert_eq!(found_pool_id, None);
+
+    // The old tier is free for a new pool
+    let pool_id: u64 = call!(
+        root,
+        amm.add_pool(ft_a.account_id(), ft_b.account_id(), 30),
+        deposit = 1
+    )
+    .unwrap_json();
+    assert_eq!(pool_id, 2);
+}
+
+#[test]
+fn pool_info_with_metadata_and_prices() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init(initial_balance);
+    register_amm_user(&amm, &alice);
+    deposit_tokens(&root, &ft_a, &alice, 100_000);
+    deposit_tokens(&root, &ft_b, &alice, 100_000);
+    call!(
+        alice,
+        amm.add_tokens_to_pool(
+            ft_a.account_id(),
+            50_000.into(),
+            ft_b.account_id(),
+            100_000.into(),
+            None
+        )
+    )
+    .assert_success();
+    call!(
+        root,
+        amm.add_pool(ft_a.account_id(), ft_b.account_id(), 100),
+        deposit = 1
+    )
+    .assert_success();
+
+    let info: Option<PoolInfo> = view!(amm.get_pool_info(0)).unwrap_json();
+    let info = info.unwrap();
+    assert_eq!(
+        info.symbols,
+        vec!["EXAMPLE".to_string(), "EXAMPLE".to_string()]
+    );
+    assert_eq!(info.decimals, vec![3, 3]);
+    assert_eq!(info.fee_bps, 30);
+    assert_eq!(info.shares_total_supply.0, 150_000);
+    // One A costs 2 B
+    let index_a = info
+        .token_ids
+        .iter()
+        .position(|token_id| token_id == &ft_a.account_id())
+        .unwrap();
+    assert_eq!(info.reserves[index_a].0, 50_000);
+    assert_eq!(info.prices[index_a].0, 2 * PRICE_PRECISION);
+    assert_eq!(info.prices[1 - index_a].0, PRICE_PRECISION / 2);
+
+    // Empty pools have no price
+    let pools: Vec<PoolInfo> = view!(amm.get_pools(0, 10)).unwrap_json();
+    assert_eq!(pools.len(), 2);
+    let pools: Vec<PoolInfo> = view!(amm.get_pools(1, 10)).unwrap_json();
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].pool_id, 1);
+    assert_eq!(pools[0].prices[0].0, 0);
+}
+
+#[test]
+fn liquidity_providers_and_depositors_are_listed() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    register_user(FT_A_ID, &bob);
+    register_amm_user(&amm, &bob);
+    deposit_tokens(&root, &ft_a, &bob, 10_000);
+
+    let providers: Vec<(AccountId, U128)> =
+        view!(amm.get_liquidity_providers(0, 0, 10)).unwrap_json();
+    assert_eq!(
+        providers,
+        vec![(alice.account_id(), U128::from(100_000 - MIN_LIQUIDITY))]
+    );
+
+    let depositors: Vec<(AccountId, U128)> =
+        view!(amm.get_depositors(ft_a.account_id(), 0, 10)).unwrap_json();
+    assert_eq!(
+        depositors,
+        vec![
+            (alice.account_id(), U128::from(50_000)),
+            (bob.account_id(), U128::from(10_000))
+        ]
+    );
+    let depositors: Vec<(AccountId, U128)> =
+        view!(amm.get_depositors(ft_b.account_id(), 1, 10)).unwrap_json();
+    assert!(depositors.is_empty());
+
+    // Providers leave the list with their share records
+    call!(
+        alice,
+        amm.exclude_tokens_from_pool(ft_a.account_id(), ft_b.account_id(), None)
+    )
+    .assert_success();
+    let providers: Vec<(AccountId, U128)> =
+        view!(amm.get_liquidity_providers(0, 0, 10)).unwrap_json();
+    assert!(providers.is_empty());
+}
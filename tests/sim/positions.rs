@@ -0,0 +1,81 @@
+use amm::{PositionView, MIN_LIQUIDITY};
+use near_sdk_sim::{call, to_yocto, view};
+
+use crate::utils::{deposit_tokens, init_with_pool, register_amm_user, register_user, FT_A_ID};
+
+#[test]
+fn position_shows_fees_and_impermanent_loss() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    register_user(FT_A_ID, &bob);
+    register_amm_user(&amm, &bob);
+    deposit_tokens(&root, &ft_a, &bob, 20_000);
+
+    // Right after the entry there are no fees and no loss
+    let position: Option<PositionView> =
+        view!(amm.get_position(alice.account_id(), 0)).unwrap_json();
+    let position = position.unwrap();
+    assert_eq!(position.shares.0, 100_000 - MIN_LIQUIDITY);
+    assert_eq!(position.amounts, position.entry_amounts);
+    assert!(position.fees.iter().all(|fee| fee.0 == 0));
+    assert_eq!(position.impermanent_loss_bps, 0);
+    let position: Option<PositionView> = view!(amm.get_position(bob.account_id(), 0)).unwrap_json();
+    assert!(position.is_none());
+
+    // Bob moves the price, so the position earns fees and loses against holding
+    call!(
+        bob,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            20_000.into(),
+            None,
+            None,
+            None
+        )
+    )
+    .assert_success();
+    let position: Option<PositionView> =
+        view!(amm.get_position(alice.account_id(), 0)).unwrap_json();
+    let position = position.unwrap();
+    assert_eq!(position.entry_amounts[0].0, 49_500);
+    assert_eq!(position.entry_amounts[1].0, 49_500);
+    assert!(position.fees.iter().all(|fee| fee.0 > 0));
+    assert!(position.impermanent_loss_bps > 0);
+
+    // Staked shares keep their entry even if the share record is closed meanwhile
+    call!(
+        root,
+        amm.set_farm(0, ft_a.account_id(), 0.into()),
+        deposit = 1
+    )
+    .assert_success();
+    call!(alice, amm.stake_shares(0, 60_000.into(), None)).assert_success();
+    call!(
+        alice,
+        amm.exclude_tokens_from_pool(ft_a.account_id(), ft_b.account_id(), None)
+    )
+    .assert_success();
+    let position: Option<PositionView> =
+        view!(amm.get_position(alice.account_id(), 0)).unwrap_json();
+    assert!(position.is_none());
+    call!(alice, amm.unstake_shares(0, None)).assert_success();
+    let position: Option<PositionView> =
+        view!(amm.get_position(alice.account_id(), 0)).unwrap_json();
+    let position = position.unwrap();
+    assert_eq!(position.shares.0, 60_000);
+    assert_eq!(position.entry_amounts[0].0, 30_000);
+    assert_eq!(position.entry_amounts[1].0, 30_000);
+    assert!(position.fees.iter().all(|fee| fee.0 > 0));
+
+    // The position is closed with the share record
+    call!(
+        alice,
+        amm.exclude_tokens_from_pool(ft_a.account_id(), ft_b.account_id(), None)
+    )
+    .assert_success();
+    let position: Option<PositionView> =
+        view!(amm.get_position(alice.account_id(), 0)).unwrap_json();
+    assert!(position.is_none());
+}
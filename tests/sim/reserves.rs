@@ -0,0 +1,142 @@
+use amm::{AmmError, PoolView, MIN_LIQUIDITY};
+use near_sdk::json_types::U128;
+use near_sdk_sim::{call, view};
+
+use crate::utils::{
+    assert_error, deposit_tokens, init, init_with_pool, pool_reserve, register_amm_user, AMM_ID,
+};
+
+#[test]
+fn first_liquidity_locks_min_liquidity() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init(initial_balance);
+    register_amm_user(&amm, &alice);
+    deposit_tokens(&root, &ft_a, &alice, 100_000);
+    deposit_tokens(&root, &ft_b, &alice, 100_000);
+
+    // Nothing can be removed from the empty pool
+    let outcome = call!(
+        alice,
+        amm.exclude_tokens_from_pool(ft_a.account_id(), ft_b.account_id(), None)
+    );
+    assert_error(outcome, AmmError::PoolHasNoLiquidity);
+
+    // First liquidity must have both tokens and be above the locked shares
+    let outcome = call!(
+        alice,
+        amm.add_tokens_to_pool(
+            ft_a.account_id(),
+            10_000.into(),
+            ft_b.account_id(),
+            0.into(),
+            None
+        )
+    );
+    assert_error(outcome, AmmError::ZeroInitialLiquidity);
+    let outcome = call!(
+        alice,
+        amm.add_tokens_to_pool(
+            ft_a.account_id(),
+            400.into(),
+            ft_b.account_id(),
+            400.into(),
+            None
+        )
+    );
+    assert_error(outcome, AmmError::LiquidityTooSmall);
+
+    call!(
+        alice,
+        amm.add_tokens_to_pool(
+            ft_a.account_id(),
+            1_000.into(),
+            ft_b.account_id(),
+            1_000.into(),
+            None
+        )
+    )
+    .assert_success();
+    let shares: U128 = view!(amm.get_shares(0, alice.account_id())).unwrap_json();
+    assert_eq!(shares.0, 2_000 - MIN_LIQUIDITY);
+    let pool: PoolView = view!(amm.get_pool(0)).unwrap_json();
+    assert_eq!(pool.shares_total_supply.0, 2_000);
+
+    // Removed liquidity leaves tokens of the locked shares in the pool
+    call!(
+        alice,
+        amm.exclude_tokens_from_pool(ft_a.account_id(), ft_b.account_id(), None)
+    )
+    .assert_success();
+    let pool: PoolView = view!(amm.get_pool(0)).unwrap_json();
+    assert_eq!(pool.shares_total_supply.0, MIN_LIQUIDITY);
+    assert_eq!(pool.reserves, vec![U128(500), U128(500)]);
+}
+
+#[test]
+fn excess_balance_is_skimmed_and_synced() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+
+    // Tokens sent without `ft_transfer_call` aren't held for anybody
+    for token in [&ft_a, &ft_b] {
+        call!(
+            root,
+            token.ft_transfer(AMM_ID.parse().unwrap(), 1_000.into(), None),
+            deposit = 1
+        )
+        .assert_success();
+    }
+    let held: U128 = view!(amm.get_held_balance(ft_a.account_id())).unwrap_json();
+    assert_eq!(held.0, 100_000);
+
+    // Only owner skims, the excess goes to the treasury
+    let outcome = call!(
+        alice,
+        amm.skim(ft_a.account_id(), 1_000.into()),
+        deposit = 1
+    );
+    assert_error(outcome, AmmError::NotOwner);
+    let root_a_before: U128 = view!(ft_a.ft_balance_of(root.account_id())).unwrap_json();
+    call!(
+        root,
+        amm.skim(ft_a.account_id(), 400.into()),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let root_a_after: U128 = view!(ft_a.ft_balance_of(root.account_id())).unwrap_json();
+    assert_eq!(root_a_after.0, root_a_before.0 + 400);
+
+    // Not more than the excess is skimmed
+    call!(
+        root,
+        amm.skim(ft_a.account_id(), 1_000.into()),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let root_a_after: U128 = view!(ft_a.ft_balance_of(root.account_id())).unwrap_json();
+    assert_eq!(root_a_after.0, root_a_before.0 + 1_000);
+
+    // Sync adds the excess of B to the reserves, A has no excess after the skim
+    call!(
+        root,
+        amm.sync(0, ft_a.account_id(), 1_000.into()),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    call!(
+        root,
+        amm.sync(0, ft_b.account_id(), 1_000.into()),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let reserve_a: U128 = pool_reserve(&amm, 0, &ft_a.account_id());
+    let reserve_b: U128 = pool_reserve(&amm, 0, &ft_b.account_id());
+    assert_eq!(reserve_a.0, 50_000);
+    assert_eq!(reserve_b.0, 51_000);
+    let held: U128 = view!(amm.get_held_balance(ft_b.account_id())).unwrap_json();
+    assert_eq!(held.0, 101_000);
+}
@@ -0,0 +1,71 @@
+use amm::{AmmError, SwapResult};
+use near_sdk_sim::call;
+
+use crate::utils::{assert_error, init_with_pool};
+
+#[test]
+fn swap_is_limited_by_price_impact_and_min_amount_out() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+
+    // Only owner limits the price impact
+    let outcome = call!(alice, amm.set_max_price_impact(0, Some(1_000)), deposit = 1);
+    assert_error(outcome, AmmError::NotOwner);
+    call!(root, amm.set_max_price_impact(0, Some(1_000)), deposit = 1).assert_success();
+
+    // Selling 20% of the reserve moves the price by about 30%
+    let outcome = call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            None,
+            None
+        )
+    );
+    assert_error(outcome, AmmError::PriceImpactTooHigh);
+
+    // Small swap passes the limit, but not the minimum amount of the buyer
+    let outcome = call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            1_000.into(),
+            Some(1_000.into()),
+            None,
+            None
+        )
+    );
+    assert_error(outcome, AmmError::BuyAmountTooSmall);
+    let result: SwapResult = call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            1_000.into(),
+            Some(900.into()),
+            None,
+            None
+        )
+    )
+    .unwrap_json();
+    assert!(result.buy_amount.0 >= 900);
+
+    // Without the limit any swap passes
+    call!(root, amm.set_max_price_impact(0, None), deposit = 1).assert_success();
+    call!(
+        alice,
+        amm.swap(
+            ft_b.account_id(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            None,
+            None
+        )
+    )
+    .assert_success();
+}
@@ -0,0 +1,43 @@
+use amm::PoolStatsView;
+use near_sdk_sim::{call, view};
+
+use crate::utils::init_with_pool;
+
+#[test]
+fn pool_stats_count_swaps() {
+    let initial_balance = 1_000_000_u128;
+    let (_root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+
+    let stats: PoolStatsView = view!(amm.get_pool_stats(0)).unwrap_json();
+    assert_eq!(stats.swaps, 0);
+    assert!(stats.history.is_empty());
+
+    // Both swaps sell A, 0.3% of every swap is the fee
+    for sell_amount in [10_000, 5_000] {
+        call!(
+            alice,
+            amm.swap(
+                ft_b.account_id(),
+                ft_a.account_id(),
+                sell_amount.into(),
+                None,
+                None,
+                None
+            )
+        )
+        .assert_success();
+    }
+    let stats: PoolStatsView = view!(amm.get_pool_stats(0)).unwrap_json();
+    let a = stats
+        .token_ids
+        .iter()
+        .position(|token_id| token_id == &ft_a.account_id())
+        .unwrap();
+    assert_eq!(stats.swaps, 2);
+    assert_eq!(stats.volumes[a].0, 15_000);
+    assert_eq!(stats.volumes[1 - a].0, 0);
+    assert_eq!(stats.fees[a].0, 45);
+    assert_eq!(stats.volumes_24h[a].0, 15_000);
+    assert_eq!(stats.history.len(), 1);
+    assert_eq!(stats.history[0].volumes[a].0, 15_000);
+}
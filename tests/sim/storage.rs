@@ -0,0 +1,193 @@
+use crate::utils::{assert_error, init, AMM_ID};
+use amm::AmmError;
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
+use near_sdk::json_types::U128;
+use near_sdk_sim::{call, to_yocto, view};
+
+#[test]
+fn storage_deposit_respects_bounds() {
+    let (root, _, _, amm, alice) = init(1_000_000);
+
+    let bounds: StorageBalanceBounds = view!(amm.storage_balance_bounds()).unwrap_json();
+    assert!(bounds.min.0 > 0);
+    assert!(bounds.max.is_none());
+
+    // Deposit below the minimum is rejected
+    let outcome = call!(
+        root,
+        amm.storage_deposit(Some(alice.account_id()), None),
+        deposit = bounds.min.0 - 1
+    );
+    assert_error(outcome, AmmError::StorageDepositTooSmall);
+    let balance: Option<StorageBalance> =
+        view!(amm.storage_balance_of(alice.account_id())).unwrap_json();
+    assert!(balance.is_none());
+
+    // Registration only keeps the minimal balance and refunds the rest
+    call!(
+        root,
+        amm.storage_deposit(Some(alice.account_id()), Some(true)),
+        deposit = to_yocto("1")
+    )
+    .assert_success();
+    let balance: StorageBalance = view!(amm.storage_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(balance.total.0, bounds.min.0);
+    assert_eq!(balance.available.0, 0);
+
+    // Deposit to the registered account increases its balance
+    call!(
+        alice,
+        amm.storage_deposit(None, None),
+        deposit = to_yocto("0.01")
+    )
+    .assert_success();
+    let balance: StorageBalance = view!(amm.storage_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(balance.total.0, bounds.min.0 + to_yocto("0.01"));
+    assert_eq!(balance.available.0, to_yocto("0.01"));
+}
+
+#[test]
+fn storage_is_charged_for_deposits_and_shares() {
+    let (root, ft_a, ft_b, amm, alice) = init(1_000_000);
+    call!(
+        root,
+        ft_a.ft_transfer(alice.account_id(), 100_000.into(), None),
+        deposit = 1
+    )
+    .assert_success();
+    call!(
+        root,
+        ft_b.ft_transfer(alice.account_id(), 100_000.into(), None),
+        deposit = 1
+    )
+    .assert_success();
+    call!(
+        alice,
+        amm.storage_deposit(None, Some(true)),
+        deposit = to_yocto("1")
+    )
+    .assert_success();
+
+    // Minimal balance doesn't cover a new deposit, so tokens are returned to Alice
+    call!(
+        alice,
+        ft_a.ft_transfer_call(AMM_ID.parse().unwrap(), 10_000.into(), None, "".to_string()),
+        deposit = 1
+    );
+    let alice_balance_ft_a: U128 = view!(ft_a.ft_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(alice_balance_ft_a.0, 100_000);
+    let alice_deposit_a: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_deposit_a.0, 0);
+
+    // With enough storage each deposited token takes a part of the available balance
+    call!(
+        alice,
+        amm.storage_deposit(None, None),
+        deposit = to_yocto("0.01")
+    )
+    .assert_success();
+    for ft in [&ft_a, &ft_b] {
+        call!(
+            alice,
+            ft.ft_transfer_call(AMM_ID.parse().unwrap(), 10_000.into(), None, "".to_string()),
+            deposit = 1
+        )
+        .assert_success();
+    }
+    let balance_with_deposits: StorageBalance =
+        view!(amm.storage_balance_of(alice.account_id())).unwrap_json();
+    assert!(balance_with_deposits.available.0 < to_yocto("0.01"));
+
+    // LP share record is charged as well and released after excluding liquidity
+    call!(
+        alice,
+        amm.add_tokens_to_pool(
+            ft_a.account_id(),
+            1_000.into(),
+            ft_b.account_id(),
+            1_000.into(),
+            None
+        )
+    )
+    .assert_success();
+    let balance_with_shares: StorageBalance =
+        view!(amm.storage_balance_of(alice.account_id())).unwrap_json();
+    assert!(balance_with_shares.available.0 < balance_with_deposits.available.0);
+
+    call!(
+        alice,
+        amm.exclude_tokens_from_pool(ft_a.account_id(), ft_b.account_id(), None)
+    )
+    .assert_success();
+    let balance_without_shares: StorageBalance =
+        view!(amm.storage_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(
+        balance_without_shares.available.0,
+        balance_with_deposits.available.0
+    );
+}
+
+#[test]
+fn storage_withdraw_and_unregister() {
+    let (root, ft_a, _, amm, alice) = init(1_000_000);
+    call!(
+        root,
+        ft_a.ft_transfer(alice.account_id(), 100_000.into(), None),
+        deposit = 1
+    )
+    .assert_success();
+    call!(
+        alice,
+        amm.storage_deposit(None, None),
+        deposit = to_yocto("0.01")
+    )
+    .assert_success();
+    call!(
+        alice,
+        ft_a.ft_transfer_call(AMM_ID.parse().unwrap(), 10_000.into(), None, "".to_string()),
+        deposit = 1
+    )
+    .assert_success();
+
+    // Only the available balance can be withdrawn
+    let balance: StorageBalance = view!(amm.storage_balance_of(alice.account_id())).unwrap_json();
+    let outcome = call!(
+        alice,
+        amm.storage_withdraw(Some(U128::from(balance.available.0 + 1))),
+        deposit = 1
+    );
+    assert_error(outcome, AmmError::StorageWithdrawTooLarge);
+    call!(alice, amm.storage_withdraw(None), deposit = 1).assert_success();
+    let balance: StorageBalance = view!(amm.storage_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(balance.available.0, 0);
+
+    // Account with deposits can't be unregistered without force
+    let outcome = call!(alice, amm.storage_unregister(None), deposit = 1);
+    assert_error(outcome, AmmError::UnregisterWithDeposits);
+
+    call!(
+        alice,
+        amm.withdraw_tokens(ft_a.account_id(), 10_000.into()),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+
+    // Unregistering refunds the whole storage balance
+    let near_before = alice.account().unwrap().amount;
+    let outcome = call!(alice, amm.storage_unregister(None), deposit = 1);
+    outcome.assert_success();
+    let unregistered: bool = outcome.unwrap_json();
+    assert!(unregistered);
+    let near_after = alice.account().unwrap().amount;
+    assert!(near_after > near_before);
+    let balance: Option<StorageBalance> =
+        view!(amm.storage_balance_of(alice.account_id())).unwrap_json();
+    assert!(balance.is_none());
+
+    // Second unregister returns false
+    let outcome = call!(alice, amm.storage_unregister(None), deposit = 1);
+    let unregistered: bool = outcome.unwrap_json();
+    assert!(!unregistered);
+}
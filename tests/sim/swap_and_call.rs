@@ -0,0 +1,102 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk_sim::{call, to_yocto, view};
+
+use crate::utils::{deploy_receiver, init_with_pool, pool_reserve, AMM_ID, RECEIVER_ID};
+
+#[test]
+fn swap_and_call_delivers_bought_tokens_to_receiver() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+    call!(
+        root,
+        ft_a.ft_transfer(alice.account_id(), 20_000.into(), None),
+        deposit = 1
+    )
+    .assert_success();
+
+    // Receiver keeps tokens it gets from AMM in the deposit of AMM
+    let receiver = deploy_receiver(&root, &ft_a, &ft_b);
+    call!(
+        root,
+        receiver.storage_deposit(Some(AMM_ID.parse().unwrap()), None),
+        deposit = to_yocto("0.1")
+    )
+    .assert_success();
+    let swap_and_call = |receiver_id: &str, min_amount_out: Option<U128>| {
+        json!({
+            "swap_and_call": {
+                "buy_token_id": ft_b.account_id(),
+                "min_amount_out": min_amount_out,
+                "receiver_id": receiver_id,
+                "msg": "",
+            }
+        })
+        .to_string()
+    };
+
+    // Failed swap refunds sold tokens to the wallet
+    call!(
+        alice,
+        ft_a.ft_transfer_call(
+            AMM_ID.parse().unwrap(),
+            10_000.into(),
+            None,
+            swap_and_call(RECEIVER_ID, Some(10_000.into()))
+        ),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let alice_wallet_a: U128 = view!(ft_a.ft_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(alice_wallet_a.0, 20_000);
+    assert_eq!(pool_reserve(&amm, 0, &ft_b.account_id()).0, 50_000);
+
+    call!(
+        alice,
+        ft_a.ft_transfer_call(
+            AMM_ID.parse().unwrap(),
+            10_000.into(),
+            None,
+            swap_and_call(RECEIVER_ID, Some(1_000.into()))
+        ),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let bought = 50_000 - pool_reserve(&amm, 0, &ft_b.account_id()).0;
+    assert!(bought > 1_000);
+    let received: U128 =
+        view!(receiver.get_deposit(AMM_ID.parse().unwrap(), ft_b.account_id())).unwrap_json();
+    assert_eq!(received.0, bought);
+    let receiver_b: U128 = view!(ft_b.ft_balance_of(RECEIVER_ID.parse().unwrap())).unwrap_json();
+    assert_eq!(receiver_b.0, bought);
+    let alice_b: U128 = view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_b.0, 50_000);
+    let locked: U128 =
+        view!(amm.get_locked_balance(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(locked.0, 0);
+    let held: U128 = view!(amm.get_held_balance(ft_b.account_id())).unwrap_json();
+    assert_eq!(held.0, 100_000 - bought);
+
+    // Carol isn't registered in token B, so the transfer fails and bought tokens
+    // go to the deposit of the sender
+    let reserve_b = pool_reserve(&amm, 0, &ft_b.account_id()).0;
+    call!(
+        alice,
+        ft_a.ft_transfer_call(
+            AMM_ID.parse().unwrap(),
+            10_000.into(),
+            None,
+            swap_and_call("carol", None)
+        ),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let bought = reserve_b - pool_reserve(&amm, 0, &ft_b.account_id()).0;
+    let alice_b: U128 = view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_b.0, 50_000 + bought);
+    let alice_wallet_a: U128 = view!(ft_a.ft_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(alice_wallet_a.0, 0);
+}
@@ -1,5 +1,9 @@
-use crate::utils::{init, AMM_ID};
+use std::collections::HashMap;
+
+use crate::utils::{init, pool_reserve, AMM_ID};
+use amm::{SwapResult, MIN_LIQUIDITY};
 use near_sdk::json_types::U128;
+use near_sdk::AccountId;
 use near_sdk_sim::{call, to_yocto, view};
 
 #[test]
@@ -57,14 +61,8 @@ fn send_tokens_to_amm_and_withdraw() {
     // Open storage in AMM for Alice
     call!(
         root,
-        amm.storage_deposit(ft_a.account_id(), alice.account_id(), None),
-        deposit = near_sdk::env::storage_byte_cost() * 125
-    )
-    .assert_success();
-    call!(
-        root,
-        amm.storage_deposit(ft_b.account_id(), alice.account_id(), None),
-        deposit = near_sdk::env::storage_byte_cost() * 125
+        amm.storage_deposit(Some(alice.account_id()), None),
+        deposit = to_yocto("0.1")
     )
     .assert_success();
 
@@ -126,10 +124,27 @@ fn send_tokens_to_amm_and_withdraw() {
     assert_eq!(transfer_to_amm_amount_a, alice_balance_amm_a.0);
     assert_eq!(transfer_to_amm_amount_b, alice_balance_amm_b.0);
 
+    // Check Alice deposits in AMM ledger
+    let alice_deposits: HashMap<AccountId, U128> =
+        view!(amm.get_deposits(alice.account_id())).unwrap_json();
+    assert_eq!(alice_deposits.len(), 2);
+    assert_eq!(
+        alice_deposits[&ft_a.account_id()].0,
+        transfer_to_amm_amount_a
+    );
+    assert_eq!(
+        alice_deposits[&ft_b.account_id()].0,
+        transfer_to_amm_amount_b
+    );
+    let alice_deposit_a: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_deposit_a.0, transfer_to_amm_amount_a);
+
     // Withdraw all tokens back
     call!(
         alice,
         amm.withdraw_tokens(ft_a.account_id(), alice_balance_amm_a),
+        deposit = 1,
         gas = 300000000000000
     )
     .assert_success();
@@ -137,6 +152,7 @@ fn send_tokens_to_amm_and_withdraw() {
     call!(
         alice,
         amm.withdraw_tokens(ft_b.account_id(), alice_balance_amm_b),
+        deposit = 1,
         gas = 300000000000000
     )
     .assert_success();
@@ -183,14 +199,8 @@ fn test_add_and_exclude_from_pool() {
     // Open storage in AMM for Alice
     call!(
         root,
-        amm.storage_deposit(ft_a.account_id(), alice.account_id(), None),
-        deposit = near_sdk::env::storage_byte_cost() * 125
-    )
-    .assert_success();
-    call!(
-        root,
-        amm.storage_deposit(ft_b.account_id(), alice.account_id(), None),
-        deposit = near_sdk::env::storage_byte_cost() * 125
+        amm.storage_deposit(Some(alice.account_id()), None),
+        deposit = to_yocto("0.1")
     )
     .assert_success();
 
@@ -219,12 +229,6 @@ fn test_add_and_exclude_from_pool() {
         deposit = 1
     )
     .assert_success();
-    call!(
-        root,
-        amm.storage_deposit(amm.account_id(), alice.account_id(), None),
-        deposit = near_sdk::env::storage_byte_cost() * 250
-    )
-    .assert_success();
 
     // Add tokens to pool
     let send_a_tokens_to_pool = 10_000_u128;
@@ -239,7 +243,8 @@ fn test_add_and_exclude_from_pool() {
             ft_a.account_id(),
             send_a_tokens_to_pool.into(),
             ft_b.account_id(),
-            send_b_tokens_to_pool.into()
+            send_b_tokens_to_pool.into(),
+            None
         )
     )
     .assert_success();
@@ -248,16 +253,13 @@ fn test_add_and_exclude_from_pool() {
         view!(amm.ft_balance_of(ft_a.account_id(), alice.account_id())).unwrap_json();
     let alice_balance_amm_b: U128 =
         view!(amm.ft_balance_of(ft_b.account_id(), alice.account_id())).unwrap_json();
-    let alice_balance_amm_amm: U128 =
-        view!(amm.ft_balance_of(amm.account_id(), alice.account_id())).unwrap_json();
-    let owner_balance_amm_a: U128 =
-        view!(amm.ft_balance_of(ft_a.account_id(), amm.account_id())).unwrap_json();
-    let owner_balance_amm_b: U128 =
-        view!(amm.ft_balance_of(ft_b.account_id(), amm.account_id())).unwrap_json();
+    let alice_balance_amm_amm: U128 = view!(amm.get_shares(0, alice.account_id())).unwrap_json();
+    let owner_balance_amm_a: U128 = pool_reserve(&amm, 0, &ft_a.account_id());
+    let owner_balance_amm_b: U128 = pool_reserve(&amm, 0, &ft_b.account_id());
 
     assert_eq!(
         alice_balance_amm_amm.0,
-        send_a_tokens_to_pool + send_b_tokens_to_pool
+        send_a_tokens_to_pool + send_b_tokens_to_pool - MIN_LIQUIDITY
     );
     assert_eq!(
         alice_balance_amm_a.0,
@@ -271,7 +273,7 @@ fn test_add_and_exclude_from_pool() {
     assert_eq!(owner_balance_amm_b.0, send_b_tokens_to_pool);
     call!(
         alice,
-        amm.exclude_tokens_from_pool(ft_a.account_id(), ft_b.account_id())
+        amm.exclude_tokens_from_pool(ft_a.account_id(), ft_b.account_id(), None)
     )
     .assert_success();
 
@@ -279,17 +281,21 @@ fn test_add_and_exclude_from_pool() {
         view!(amm.ft_balance_of(ft_a.account_id(), alice.account_id())).unwrap_json();
     let alice_balance_amm_b: U128 =
         view!(amm.ft_balance_of(ft_b.account_id(), alice.account_id())).unwrap_json();
-    let alice_balance_amm_amm: U128 =
-        view!(amm.ft_balance_of(amm.account_id(), alice.account_id())).unwrap_json();
-    let owner_balance_amm_a: U128 =
-        view!(amm.ft_balance_of(ft_a.account_id(), amm.account_id())).unwrap_json();
-    let owner_balance_amm_b: U128 =
-        view!(amm.ft_balance_of(ft_b.account_id(), amm.account_id())).unwrap_json();
+    let alice_balance_amm_amm: U128 = view!(amm.get_shares(0, alice.account_id())).unwrap_json();
+    let owner_balance_amm_a: U128 = pool_reserve(&amm, 0, &ft_a.account_id());
+    let owner_balance_amm_b: U128 = pool_reserve(&amm, 0, &ft_b.account_id());
+    // Tokens of the locked shares stay in the pool
     assert_eq!(alice_balance_amm_amm.0, 0);
-    assert_eq!(alice_balance_amm_a.0, alice_balance_amm_a_before.0);
-    assert_eq!(alice_balance_amm_b.0, alice_balance_amm_b_before.0);
-    assert_eq!(owner_balance_amm_a.0, 0);
-    assert_eq!(owner_balance_amm_b.0, 0);
+    assert_eq!(owner_balance_amm_a.0, 334);
+    assert_eq!(owner_balance_amm_b.0, 667);
+    assert_eq!(
+        alice_balance_amm_a.0 + owner_balance_amm_a.0,
+        alice_balance_amm_a_before.0
+    );
+    assert_eq!(
+        alice_balance_amm_b.0 + owner_balance_amm_b.0,
+        alice_balance_amm_b_before.0
+    );
 }
 
 #[test]
@@ -318,14 +324,8 @@ fn test_swap_tokens() {
     // Open storage in AMM for Alice
     call!(
         root,
-        amm.storage_deposit(ft_a.account_id(), alice.account_id(), None),
-        deposit = near_sdk::env::storage_byte_cost() * 125
-    )
-    .assert_success();
-    call!(
-        root,
-        amm.storage_deposit(ft_b.account_id(), alice.account_id(), None),
-        deposit = near_sdk::env::storage_byte_cost() * 125
+        amm.storage_deposit(Some(alice.account_id()), None),
+        deposit = to_yocto("0.1")
     )
     .assert_success();
 
@@ -354,12 +354,6 @@ fn test_swap_tokens() {
         deposit = 1
     )
     .assert_success();
-    call!(
-        root,
-        amm.storage_deposit(amm.account_id(), alice.account_id(), None),
-        deposit = near_sdk::env::storage_byte_cost() * 250
-    )
-    .assert_success();
 
     // Add tokens to pool
     let send_a_tokens_to_pool = 30_000_u128;
@@ -374,7 +368,8 @@ fn test_swap_tokens() {
             ft_a.account_id(),
             send_a_tokens_to_pool.into(),
             ft_b.account_id(),
-            send_b_tokens_to_pool.into()
+            send_b_tokens_to_pool.into(),
+            None
         )
     )
     .assert_success();
@@ -382,15 +377,12 @@ fn test_swap_tokens() {
         view!(amm.ft_balance_of(ft_a.account_id(), alice.account_id())).unwrap_json();
     let alice_balance_amm_b: U128 =
         view!(amm.ft_balance_of(ft_b.account_id(), alice.account_id())).unwrap_json();
-    let alice_balance_amm_amm: U128 =
-        view!(amm.ft_balance_of(amm.account_id(), alice.account_id())).unwrap_json();
-    let owner_balance_amm_a: U128 =
-        view!(amm.ft_balance_of(ft_a.account_id(), amm.account_id())).unwrap_json();
-    let owner_balance_amm_b: U128 =
-        view!(amm.ft_balance_of(ft_b.account_id(), amm.account_id())).unwrap_json();
+    let alice_balance_amm_amm: U128 = view!(amm.get_shares(0, alice.account_id())).unwrap_json();
+    let owner_balance_amm_a: U128 = pool_reserve(&amm, 0, &ft_a.account_id());
+    let owner_balance_amm_b: U128 = pool_reserve(&amm, 0, &ft_b.account_id());
     assert_eq!(
         alice_balance_amm_amm.0,
-        send_a_tokens_to_pool + send_b_tokens_to_pool
+        send_a_tokens_to_pool + send_b_tokens_to_pool - MIN_LIQUIDITY
     );
     assert_eq!(
         alice_balance_amm_a.0,
@@ -412,28 +404,31 @@ fn test_swap_tokens() {
     let alice_balance_amm_b_prev: U128 =
         view!(amm.ft_balance_of(ft_b.account_id(), alice.account_id())).unwrap_json();
     let alice_balance_amm_amm_prev: U128 =
-        view!(amm.ft_balance_of(amm.account_id(), alice.account_id())).unwrap_json();
-    let owner_balance_amm_a_prev: U128 =
-        view!(amm.ft_balance_of(ft_a.account_id(), amm.account_id())).unwrap_json();
-    let owner_balance_amm_b_prev: U128 =
-        view!(amm.ft_balance_of(ft_b.account_id(), amm.account_id())).unwrap_json();
+        view!(amm.get_shares(0, alice.account_id())).unwrap_json();
+    let owner_balance_amm_a_prev: U128 = pool_reserve(&amm, 0, &ft_a.account_id());
+    let owner_balance_amm_b_prev: U128 = pool_reserve(&amm, 0, &ft_b.account_id());
     let outcome = call!(
         alice,
-        amm.swap(buy_token, sell_token, sell_token_amount.into())
+        amm.swap(
+            buy_token,
+            sell_token,
+            sell_token_amount.into(),
+            None,
+            None,
+            None
+        )
     );
     outcome.assert_success();
-    let buy_amount: U128 = outcome.unwrap_json();
+    let result: SwapResult = outcome.unwrap_json();
+    let buy_amount = result.buy_amount;
 
     let alice_balance_amm_a: U128 =
         view!(amm.ft_balance_of(ft_a.account_id(), alice.account_id())).unwrap_json();
     let alice_balance_amm_b: U128 =
         view!(amm.ft_balance_of(ft_b.account_id(), alice.account_id())).unwrap_json();
-    let alice_balance_amm_amm: U128 =
-        view!(amm.ft_balance_of(amm.account_id(), alice.account_id())).unwrap_json();
-    let owner_balance_amm_a: U128 =
-        view!(amm.ft_balance_of(ft_a.account_id(), amm.account_id())).unwrap_json();
-    let owner_balance_amm_b: U128 =
-        view!(amm.ft_balance_of(ft_b.account_id(), amm.account_id())).unwrap_json();
+    let alice_balance_amm_amm: U128 = view!(amm.get_shares(0, alice.account_id())).unwrap_json();
+    let owner_balance_amm_a: U128 = pool_reserve(&amm, 0, &ft_a.account_id());
+    let owner_balance_amm_b: U128 = pool_reserve(&amm, 0, &ft_b.account_id());
 
     assert_eq!(
         alice_balance_amm_a.0,
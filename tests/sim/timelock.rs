@@ -0,0 +1,97 @@
+use amm::{AdminAction, AmmError, QueuedActionView, TimelockConfig};
+use near_sdk::AccountId;
+use near_sdk_sim::{call, to_yocto, view};
+
+use crate::utils::{assert_error, init};
+
+#[test]
+fn owner_actions_are_executed_after_delay() {
+    let initial_balance = 1_000_000_u128;
+    let (root, _ft_a, _ft_b, amm, alice) = init(initial_balance);
+    call!(
+        root,
+        amm.set_timelock(TimelockConfig {
+            delay_sec: 10,
+            guardians: vec![alice.account_id()],
+        }),
+        deposit = 1
+    )
+    .assert_success();
+
+    // Owner methods don't apply changes immediately with the delay
+    let outcome = call!(root, amm.set_treasury(alice.account_id()), deposit = 1);
+    assert_error(outcome, AmmError::TimelockRequired);
+
+    let outcome = call!(
+        root,
+        amm.queue_action(AdminAction::SetTreasury {
+            treasury_id: alice.account_id(),
+        }),
+        deposit = to_yocto("0.1")
+    );
+    outcome.assert_success();
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"admin_action_queued\"")));
+    let action_id: u64 = outcome.unwrap_json();
+    let actions: Vec<QueuedActionView> = view!(amm.get_queued_actions(0, 10)).unwrap_json();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].action_id, action_id);
+
+    let outcome = call!(root, amm.execute_action(action_id));
+    assert_error(outcome, AmmError::ActionNotReady);
+    root.borrow_runtime_mut().produce_blocks(12).unwrap();
+    let outcome = call!(alice, amm.execute_action(action_id));
+    outcome.assert_success();
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"admin_action_executed\"")));
+    let treasury: AccountId = view!(amm.get_treasury()).unwrap_json();
+    assert_eq!(treasury, alice.account_id());
+    let action: Option<QueuedActionView> = view!(amm.get_queued_action(action_id)).unwrap_json();
+    assert!(action.is_none());
+}
+
+#[test]
+fn guardian_cancels_queued_action() {
+    let initial_balance = 1_000_000_u128;
+    let (root, _ft_a, _ft_b, amm, alice) = init(initial_balance);
+    let bob = root.create_user("bob".parse().unwrap(), to_yocto("100"));
+    call!(
+        root,
+        amm.set_timelock(TimelockConfig {
+            delay_sec: 10,
+            guardians: vec![alice.account_id()],
+        }),
+        deposit = 1
+    )
+    .assert_success();
+    let action_id: u64 = call!(
+        root,
+        amm.queue_action(AdminAction::SetOwner {
+            owner_id: bob.account_id(),
+        }),
+        deposit = to_yocto("0.1")
+    )
+    .unwrap_json();
+
+    // Only guardians cancel, the owner key alone can't
+    let outcome = call!(bob, amm.cancel_action(action_id), deposit = 1);
+    assert_error(outcome, AmmError::NotGuardian);
+    let outcome = call!(root, amm.cancel_action(action_id), deposit = 1);
+    assert_error(outcome, AmmError::NotGuardian);
+    let outcome = call!(alice, amm.cancel_action(action_id), deposit = 1);
+    outcome.assert_success();
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("\"event\":\"admin_action_cancelled\"")));
+
+    root.borrow_runtime_mut().produce_blocks(12).unwrap();
+    let outcome = call!(root, amm.execute_action(action_id));
+    assert_error(outcome, AmmError::ActionNotFound);
+    let owner: AccountId = view!(amm.get_owner()).unwrap_json();
+    assert_eq!(owner, root.account_id());
+}
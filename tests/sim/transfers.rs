@@ -0,0 +1,155 @@
+use amm::{Action, ActionAmount, AmmError};
+use amm_client::amm_methods::WithdrawTokensArgs;
+use amm_client::{AmmClient, DEFAULT_GAS};
+use near_sdk::json_types::U128;
+use near_sdk_sim::{call, to_yocto, view};
+
+use crate::utils::{
+    assert_error, deposit_tokens, init, init_with_pool, register_amm_user, register_user, AMM_ID,
+    FT_A_ID,
+};
+
+#[test]
+fn concurrent_withdrawals_can_not_double_spend() {
+    let initial_balance = 1_000_000_u128;
+    let (_root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+    let withdraw = |amount: u128| Action::Withdraw {
+        token_id: ft_a.account_id(),
+        amount: ActionAmount::Exact(amount.into()),
+    };
+
+    // Two withdrawals in one transaction run before the callback of the first one,
+    // so the second can't use tokens locked by the first
+    let amm_client = AmmClient {
+        contract_id: AMM_ID.parse().unwrap(),
+    };
+    let withdraw_call = || {
+        amm_client
+            .withdraw_tokens(WithdrawTokensArgs {
+                token_name: ft_a.account_id(),
+                amount: U128::from(30_000),
+            })
+            .with_gas(DEFAULT_GAS)
+    };
+    let (first, second) = (withdraw_call(), withdraw_call());
+    let outcome = alice
+        .create_transaction(amm.account_id())
+        .function_call(
+            first.method_name.to_string(),
+            first.args,
+            first.gas.0,
+            first.deposit,
+        )
+        .function_call(
+            second.method_name.to_string(),
+            second.args,
+            second.gas.0,
+            second.deposit,
+        )
+        .submit();
+    assert_error(outcome, AmmError::NotEnoughDeposit);
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 50_000);
+    let locked: U128 =
+        view!(amm.get_locked_balance(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(locked.0, 0);
+
+    // Same for the withdrawals in one call
+    let outcome = call!(
+        alice,
+        amm.execute(vec![withdraw(30_000), withdraw(30_000)]),
+        deposit = 1,
+        gas = 300000000000000
+    );
+    assert_error(outcome, AmmError::NotEnoughDeposit);
+
+    // Locked tokens can't be swapped before the callback either
+    let outcome = call!(
+        alice,
+        amm.execute(vec![
+            withdraw(30_000),
+            Action::Swap {
+                sell_token_id: ft_a.account_id(),
+                buy_token_id: ft_b.account_id(),
+                sell_amount: ActionAmount::Exact(30_000.into()),
+                min_amount_out: None,
+                referral_id: None,
+                fee_bps: None,
+            }
+        ]),
+        deposit = 1,
+        gas = 300000000000000
+    );
+    assert_error(outcome, AmmError::NotEnoughDeposit);
+    let alice_wallet_a: U128 = view!(ft_a.ft_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(alice_wallet_a.0, 0);
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 50_000);
+
+    // Transfers within the deposit are finalised by their callbacks
+    call!(
+        alice,
+        amm.execute(vec![withdraw(30_000), withdraw(20_000)]),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let alice_wallet_a: U128 = view!(ft_a.ft_balance_of(alice.account_id())).unwrap_json();
+    assert_eq!(alice_wallet_a.0, 50_000);
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 0);
+    let locked: U128 =
+        view!(amm.get_locked_balance(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(locked.0, 0);
+    let held: U128 = view!(amm.get_held_balance(ft_a.account_id())).unwrap_json();
+    assert_eq!(held.0, 50_000);
+
+    // Nothing is left to withdraw
+    let outcome = call!(
+        alice,
+        amm.withdraw_tokens(ft_a.account_id(), 1.into()),
+        deposit = 1,
+        gas = 300000000000000
+    );
+    assert_error(outcome, AmmError::NotEnoughDeposit);
+}
+
+#[test]
+fn failed_transfer_is_reverted_to_deposit() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, _, amm, _) = init(initial_balance);
+    let carol = root.create_user("carol".parse().unwrap(), to_yocto("100"));
+    register_user(FT_A_ID, &carol);
+    register_amm_user(&amm, &carol);
+    deposit_tokens(&root, &ft_a, &carol, 10_000);
+
+    // Carol leaves the token, so the transfer to her wallet fails
+    call!(carol, ft_a.storage_unregister(None), deposit = 1).assert_success();
+    let outcome = call!(
+        carol,
+        amm.withdraw_tokens(ft_a.account_id(), 10_000.into()),
+        deposit = 1,
+        gas = 300000000000000
+    );
+    let transferred: bool = outcome.unwrap_json();
+    assert!(!transferred);
+    let carol_a: U128 = view!(amm.get_deposit(carol.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(carol_a.0, 10_000);
+    let locked: U128 =
+        view!(amm.get_locked_balance(carol.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(locked.0, 0);
+    let amm_balance: U128 = view!(ft_a.ft_balance_of(AMM_ID.parse().unwrap())).unwrap_json();
+    assert_eq!(amm_balance.0, 10_000);
+
+    // Tokens of the reverted transfer can be used again
+    register_user(FT_A_ID, &carol);
+    call!(
+        carol,
+        amm.withdraw_tokens(ft_a.account_id(), 10_000.into()),
+        deposit = 1,
+        gas = 300000000000000
+    )
+    .assert_success();
+    let carol_wallet_a: U128 = view!(ft_a.ft_balance_of(carol.account_id())).unwrap_json();
+    assert_eq!(carol_wallet_a.0, 10_000);
+}
@@ -0,0 +1,113 @@
+use amm::{AmmError, PoolView, TwammOrderView};
+use near_sdk::json_types::U128;
+use near_sdk_sim::{call, view};
+
+use crate::utils::{assert_error, init_with_pool};
+
+#[test]
+fn twamm_order_is_executed_over_blocks() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+
+    // 1_005 tokens can't be split by 10 blocks, so 5 tokens stay in the deposit
+    let outcome = call!(
+        alice,
+        amm.place_twamm_order(ft_a.account_id(), ft_b.account_id(), 1_005.into(), 10, None)
+    );
+    outcome.assert_success();
+    let order_id: u64 = outcome.unwrap_json();
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 49_000);
+    let orders: Vec<TwammOrderView> =
+        view!(amm.get_account_twamm_orders(alice.account_id())).unwrap_json();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].sell_rate.0, 100);
+
+    // After the end all tokens are sold and the order is closed by the withdrawal.
+    // Views show the accrued sells before any interaction with the pool
+    root.borrow_runtime_mut().produce_blocks(20).unwrap();
+    let pool: PoolView = view!(amm.get_pool(0)).unwrap_json();
+    assert_eq!(pool.reserves[0].0, 51_000);
+    let order: Option<TwammOrderView> = view!(amm.get_twamm_order(order_id)).unwrap_json();
+    let accrued = order.unwrap().proceeds.0;
+    assert!(accrued > 900 && accrued < 1_000);
+    let alice_b_before: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    let outcome = call!(alice, amm.withdraw_twamm_proceeds(order_id));
+    outcome.assert_success();
+    let proceeds: U128 = outcome.unwrap_json();
+    assert_eq!(proceeds.0, accrued);
+    let alice_b_after: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert_eq!(alice_b_after.0, alice_b_before.0 + proceeds.0);
+    let order: Option<TwammOrderView> = view!(amm.get_twamm_order(order_id)).unwrap_json();
+    assert!(order.is_none());
+    let pool: PoolView = view!(amm.get_pool(0)).unwrap_json();
+    assert_eq!(pool.reserves[0].0, 51_000);
+}
+
+#[test]
+fn twamm_order_is_cancelled() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+
+    let order_id: u64 = call!(
+        alice,
+        amm.place_twamm_order(
+            ft_a.account_id(),
+            ft_b.account_id(),
+            10_000.into(),
+            1_000,
+            None
+        )
+    )
+    .unwrap_json();
+    root.borrow_runtime_mut().produce_blocks(10).unwrap();
+
+    let outcome = call!(root, amm.cancel_twamm_order(order_id));
+    assert_error(outcome, AmmError::NotTwammOrderMaker);
+
+    // Unexecuted tokens return to the deposit, executed ones are paid with proceeds
+    let alice_b_before: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    let outcome = call!(alice, amm.cancel_twamm_order(order_id));
+    outcome.assert_success();
+    let unexecuted: U128 = outcome.unwrap_json();
+    assert!(unexecuted.0 > 0 && unexecuted.0 < 10_000);
+    let alice_a: U128 = view!(amm.get_deposit(alice.account_id(), ft_a.account_id())).unwrap_json();
+    assert_eq!(alice_a.0, 40_000 + unexecuted.0);
+    let alice_b_after: U128 =
+        view!(amm.get_deposit(alice.account_id(), ft_b.account_id())).unwrap_json();
+    assert!(alice_b_after.0 > alice_b_before.0);
+    let orders: Vec<TwammOrderView> =
+        view!(amm.get_account_twamm_orders(alice.account_id())).unwrap_json();
+    assert!(orders.is_empty());
+}
+
+#[test]
+fn twamm_expiry_is_removed_with_the_last_order() {
+    let initial_balance = 1_000_000_u128;
+    let (root, ft_a, ft_b, amm, alice) = init_with_pool(initial_balance);
+    let place_order = || -> u64 {
+        call!(
+            alice,
+            amm.place_twamm_order(ft_a.account_id(), ft_b.account_id(), 1_000.into(), 10, None)
+        )
+        .unwrap_json()
+    };
+    let contract_storage = || amm.user_account.account().unwrap().storage_usage;
+
+    // The first order opens the deposit of the buy token, which stays after it
+    let order_id = place_order();
+    call!(alice, amm.cancel_twamm_order(order_id)).assert_success();
+    let storage_usage = contract_storage();
+
+    // Orders closed by the withdrawal after the end or by the cancellation leave nothing
+    let order_id = place_order();
+    root.borrow_runtime_mut().produce_blocks(20).unwrap();
+    call!(alice, amm.withdraw_twamm_proceeds(order_id)).assert_success();
+    assert_eq!(contract_storage(), storage_usage);
+    let order_id = place_order();
+    call!(alice, amm.cancel_twamm_order(order_id)).assert_success();
+    assert_eq!(contract_storage(), storage_usage);
+}
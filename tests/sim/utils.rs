@@ -1,8 +1,14 @@
-use amm::AMMContract;
+use amm::{AMMContract, AmmError, PoolView};
+use amm_client::ft_methods::StorageDepositArgs;
+use amm_client::{FtClient, FunctionCall, ViewCall};
 use ft::FtContractContract as FtContract;
 use near_contract_standards::fungible_token::metadata::{FungibleTokenMetadata, FT_METADATA_SPEC};
-use near_sdk::serde_json::json;
-use near_sdk_sim::{deploy, init_simulator, to_yocto, ContractAccount, UserAccount};
+use near_sdk::json_types::U128;
+use near_sdk::serde::de::DeserializeOwned;
+use near_sdk::{AccountId, Gas};
+use near_sdk_sim::{
+    call, deploy, init_simulator, to_yocto, view, ContractAccount, ExecutionResult, UserAccount,
+};
 
 // Load in contract bytes at runtime
 near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
@@ -13,21 +19,54 @@ near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
 pub const FT_A_ID: &str = "token_a";
 pub const FT_B_ID: &str = "token_b";
 pub const AMM_ID: &str = "amm";
+pub const RECEIVER_ID: &str = "receiver";
+pub const NEAR_TOKEN_ID: &str = "near";
 
 // Register the given `user` with FT contract
 pub fn register_user(contract_id: &str, user: &near_sdk_sim::UserAccount) {
-    user.call(
-        contract_id.parse().unwrap(),
-        "storage_deposit",
-        &json!({
-            "account_id": user.account_id()
+    let ft = FtClient {
+        contract_id: contract_id.parse().unwrap(),
+    };
+    let call = ft
+        .storage_deposit(StorageDepositArgs {
+            account_id: Some(user.account_id()),
+            registration_only: None,
         })
-        .to_string()
-        .into_bytes(),
-        near_sdk_sim::DEFAULT_GAS / 2,
-        near_sdk::env::storage_byte_cost() * 125, // attached deposit
+        .with_gas(Gas(near_sdk_sim::DEFAULT_GAS / 2))
+        .with_deposit(near_sdk::env::storage_byte_cost() * 125);
+    send(user, call).assert_success();
+}
+
+// Send the call built by the typed client
+pub fn send<T>(user: &UserAccount, call: FunctionCall<T>) -> ExecutionResult {
+    user.call(
+        call.contract_id.clone(),
+        call.method_name,
+        &call.args,
+        call.gas.0,
+        call.deposit,
     )
-    .assert_success();
+}
+
+// Run the view built by the typed client and parse its result
+pub fn send_view<T: DeserializeOwned>(user: &UserAccount, call: ViewCall<T>) -> T {
+    let result = user
+        .view(call.contract_id.clone(), call.method_name, &call.args)
+        .unwrap();
+    call.parse_result(&result).unwrap()
+}
+
+// Metadata of test tokens
+pub fn ft_metadata() -> FungibleTokenMetadata {
+    FungibleTokenMetadata {
+        spec: FT_METADATA_SPEC.to_string(),
+        name: "FT".to_string(),
+        symbol: "EXAMPLE".to_string(),
+        icon: None,
+        reference: None,
+        reference_hash: None,
+        decimals: 3,
+    }
 }
 
 pub fn init(
@@ -40,15 +79,7 @@ pub fn init(
     UserAccount,
 ) {
     let root = init_simulator(None);
-    let meta = FungibleTokenMetadata {
-        spec: FT_METADATA_SPEC.to_string(),
-        name: "FT".to_string(),
-        symbol: "EXAMPLE".to_string(),
-        icon: None,
-        reference: None,
-        reference_hash: None,
-        decimals: 3,
-    };
+    let meta = ft_metadata();
 
     // Init Token A contract
     let token_a_contract = deploy!(
@@ -102,3 +133,158 @@ pub fn init(
         alice,
     )
 }
+
+// Same as `init`, and Alice has 100_000 of both tokens in AMM, half of them in the pool
+pub fn init_with_pool(
+    initial_balance: u128,
+) -> (
+    UserAccount,
+    ContractAccount<FtContract>,
+    ContractAccount<FtContract>,
+    ContractAccount<AMMContract>,
+    UserAccount,
+) {
+    let (root, ft_a, ft_b, amm, alice) = init(initial_balance);
+    register_amm_user(&amm, &alice);
+    deposit_tokens(&root, &ft_a, &alice, 100_000);
+    deposit_tokens(&root, &ft_b, &alice, 100_000);
+    call!(
+        alice,
+        amm.add_tokens_to_pool(
+            ft_a.account_id(),
+            50_000.into(),
+            ft_b.account_id(),
+            50_000.into(),
+            None
+        )
+    )
+    .assert_success();
+    (root, ft_a, ft_b, amm, alice)
+}
+
+// Same as `init`, but the AMM pool is Token A / NEAR
+pub fn init_near_pool(
+    initial_balance: u128,
+) -> (
+    UserAccount,
+    ContractAccount<FtContract>,
+    ContractAccount<AMMContract>,
+    UserAccount,
+) {
+    let root = init_simulator(None);
+    let meta = ft_metadata();
+    let near_meta = FungibleTokenMetadata {
+        name: "NEAR".to_string(),
+        symbol: "NEAR".to_string(),
+        decimals: 24,
+        ..ft_metadata()
+    };
+
+    // Init Token A contract
+    let token_a_contract = deploy!(
+        contract: FtContract,
+        contract_id: FT_A_ID,
+        bytes: &FT_WASM_BYTES,
+        signer_account: root,
+        init_method: new(
+            root.account_id(),
+            initial_balance.into(),
+            meta.clone()
+        )
+    );
+    let alice = root.create_user("alice".parse().unwrap(), to_yocto("100"));
+    register_user(FT_A_ID, &alice);
+
+    // Init AMM contract
+    let amm_contract = deploy!(
+        contract: AMMContract,
+        contract_id: AMM_ID,
+        bytes: &AMM_WASM_BYTES,
+        signer_account: root,
+        init_method: new(
+            token_a_contract.account_id(),
+            NEAR_TOKEN_ID.parse().unwrap(),
+            meta,
+            near_meta
+        )
+    );
+    register_user(FT_A_ID, &amm_contract.user_account);
+    (root, token_a_contract, amm_contract, alice)
+}
+
+// Another AMM with the same tokens, it stands for a contract receiving tokens
+// with `ft_transfer_call` from AMM
+pub fn deploy_receiver(
+    root: &UserAccount,
+    ft_a: &ContractAccount<FtContract>,
+    ft_b: &ContractAccount<FtContract>,
+) -> ContractAccount<AMMContract> {
+    let meta = ft_metadata();
+    let receiver = deploy!(
+        contract: AMMContract,
+        contract_id: RECEIVER_ID,
+        bytes: &AMM_WASM_BYTES,
+        signer_account: root,
+        init_method: new(ft_a.account_id(), ft_b.account_id(), meta.clone(), meta)
+    );
+    register_user(FT_A_ID, &receiver.user_account);
+    register_user(FT_B_ID, &receiver.user_account);
+    receiver
+}
+
+// Register the given `user` in AMM with enough storage for deposits and LP shares
+pub fn register_amm_user(amm: &ContractAccount<AMMContract>, user: &UserAccount) {
+    call!(
+        user,
+        amm.storage_deposit(None, None),
+        deposit = to_yocto("0.1")
+    )
+    .assert_success();
+}
+
+// Send tokens from root to the `user` and deposit them to AMM
+pub fn deposit_tokens(
+    root: &UserAccount,
+    ft: &ContractAccount<FtContract>,
+    user: &UserAccount,
+    amount: u128,
+) {
+    call!(
+        root,
+        ft.ft_transfer(user.account_id(), amount.into(), None),
+        deposit = 1
+    )
+    .assert_success();
+    call!(
+        user,
+        ft.ft_transfer_call(AMM_ID.parse().unwrap(), amount.into(), None, "".to_string()),
+        deposit = 1
+    )
+    .assert_success();
+}
+
+// Amount of the token in reserves of the pool
+pub fn pool_reserve(
+    amm: &ContractAccount<AMMContract>,
+    pool_id: u64,
+    token_id: &AccountId,
+) -> U128 {
+    let pool: PoolView = view!(amm.get_pool(pool_id)).unwrap_json();
+    let index = pool.token_ids.iter().position(|id| id == token_id).unwrap();
+    pool.reserves[index]
+}
+
+// Check that the first failed receipt of the outcome contains the error message
+pub fn assert_failure(outcome: ExecutionResult, error_message: &str) {
+    assert!(!outcome.is_ok());
+    let status = format!(
+        "{:?}",
+        outcome.promise_errors()[0].as_ref().unwrap().status()
+    );
+    assert!(status.contains(error_message), "{}", status);
+}
+
+// Checks the code of the AMM error, details after the message may vary
+pub fn assert_error(outcome: ExecutionResult, error: AmmError) {
+    assert_failure(outcome, &format!("{}: ", error.code()));
+}
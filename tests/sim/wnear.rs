@@ -0,0 +1,110 @@
+use crate::utils::{assert_error, init_near_pool, pool_reserve, AMM_ID, NEAR_TOKEN_ID};
+use amm::{Action, ActionAmount, AmmError, SwapResult};
+use near_sdk::json_types::U128;
+use near_sdk::AccountId;
+use near_sdk_sim::{call, to_yocto, view};
+
+#[test]
+fn deposit_and_withdraw_near() {
+    let (_, _, amm, alice) = init_near_pool(1_000_000);
+    let near_id: AccountId = NEAR_TOKEN_ID.parse().unwrap();
+    call!(
+        alice,
+        amm.storage_deposit(None, None),
+        deposit = to_yocto("0.01")
+    )
+    .assert_success();
+
+    call!(alice, amm.deposit_near(), deposit = to_yocto("10")).assert_success();
+    let deposit: U128 = view!(amm.get_deposit(alice.account_id(), near_id.clone())).unwrap_json();
+    assert_eq!(deposit.0, to_yocto("10"));
+
+    // NEAR can't be withdrawn as FT
+    let outcome = call!(
+        alice,
+        amm.withdraw_tokens(near_id.clone(), to_yocto("1").into()),
+        deposit = 1,
+        gas = 300000000000000
+    );
+    assert_error(outcome, AmmError::NearWithdrawOnly);
+    let outcome = call!(
+        alice,
+        amm.execute(vec![Action::Withdraw {
+            token_id: near_id.clone(),
+            amount: ActionAmount::Exact(to_yocto("1").into()),
+        }]),
+        deposit = 1,
+        gas = 300000000000000
+    );
+    assert_error(outcome, AmmError::NearWithdrawOnly);
+
+    let outcome = call!(alice, amm.withdraw_near(to_yocto("11").into()), deposit = 1);
+    assert_error(outcome, AmmError::NotEnoughDeposit);
+
+    let near_before = alice.account().unwrap().amount;
+    call!(alice, amm.withdraw_near(to_yocto("4").into()), deposit = 1).assert_success();
+    let near_after = alice.account().unwrap().amount;
+    assert!(near_after > near_before + to_yocto("3.9"));
+    let deposit: U128 = view!(amm.get_deposit(alice.account_id(), near_id)).unwrap_json();
+    assert_eq!(deposit.0, to_yocto("6"));
+}
+
+#[test]
+fn swap_tokens_for_near() {
+    let (root, ft_a, amm, alice) = init_near_pool(1_000_000);
+    let near_id: AccountId = NEAR_TOKEN_ID.parse().unwrap();
+    call!(
+        root,
+        ft_a.ft_transfer(alice.account_id(), 100_000.into(), None),
+        deposit = 1
+    )
+    .assert_success();
+    call!(
+        alice,
+        amm.storage_deposit(None, None),
+        deposit = to_yocto("0.1")
+    )
+    .assert_success();
+    call!(
+        alice,
+        ft_a.ft_transfer_call(AMM_ID.parse().unwrap(), 50_000.into(), None, "".to_string()),
+        deposit = 1
+    )
+    .assert_success();
+    call!(alice, amm.deposit_near(), deposit = to_yocto("20")).assert_success();
+
+    // NEAR is added to the pool as any other token
+    call!(
+        alice,
+        amm.add_tokens_to_pool(
+            ft_a.account_id(),
+            10_000.into(),
+            near_id.clone(),
+            to_yocto("10").into(),
+            None
+        )
+    )
+    .assert_success();
+    let pool_near: U128 = pool_reserve(&amm, 0, &near_id);
+    assert_eq!(pool_near.0, to_yocto("10"));
+
+    let outcome = call!(
+        alice,
+        amm.swap(
+            near_id.clone(),
+            ft_a.account_id(),
+            10_000.into(),
+            None,
+            None,
+            None
+        )
+    );
+    outcome.assert_success();
+    let result: SwapResult = outcome.unwrap_json();
+    let buy_amount = result.buy_amount;
+    assert_eq!(result.fee_bps, 30);
+    // 0.3% fee is charged from the sold tokens
+    assert_eq!(buy_amount.0, to_yocto("10") * 9_970 / 19_970);
+    let deposit: U128 = view!(amm.get_deposit(alice.account_id(), near_id)).unwrap_json();
+    assert_eq!(deposit.0, to_yocto("10") + buy_amount.0);
+}